@@ -0,0 +1,402 @@
+use std::fmt;
+use std::ops::Range;
+
+/// Byte offsets into the source a diagnostic is rendered against.
+pub type Span = Range<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Note => f.write_str("note"),
+        }
+    }
+}
+
+/// A span of the source to underline, with a message written after the
+/// underline. Primary labels are underlined with `^`, secondary ones
+/// with `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// A message about some source text, which `render` shows together with
+/// the lines it is about:
+///
+/// ```text
+/// error: unknown symbol `?`
+///  --> 1:5
+///   |
+/// 1 | 1 2 ? +
+///   |     ^ not a number, operator or variable
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn note(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Note, message)
+    }
+
+    /// Point at what the diagnostic is about. The location in the header
+    /// is that of the first primary label.
+    pub fn with_label(self, span: Span, message: impl Into<String>) -> Self {
+        self.label(span, message.into(), true)
+    }
+
+    /// Point at something that explains the primary label.
+    pub fn with_secondary_label(self, span: Span, message: impl Into<String>) -> Self {
+        self.label(span, message.into(), false)
+    }
+
+    /// Add a line after the source, such as a hint on how to fix it.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    fn label(mut self, span: Span, message: String, primary: bool) -> Self {
+        self.labels.push(Label {
+            span,
+            message,
+            primary,
+        });
+        self
+    }
+
+    /// The full report: the message, the location of the first primary
+    /// label, every line a label touches with the label underlined below
+    /// it, and the notes.
+    ///
+    /// A label over several lines is underlined on each of them, with its
+    /// message after the last underline. Spans past the end of `source`
+    /// are cut short, and an empty span is shown as a single `^` at its
+    /// position.
+    pub fn render(&self, source: &str) -> String {
+        let lines = lines(source);
+        let segments = self.segments(source, &lines);
+        let width = segments
+            .last()
+            .map_or(1, |segment| (segment.line + 1).to_string().len());
+        let mut output = vec![self.to_string()];
+
+        let first = segments
+            .iter()
+            .find(|segment| self.labels[segment.label].primary)
+            .or(segments.first());
+        if let Some(first) = first {
+            let (_, text) = lines[first.line];
+            let column = text[..first.start].chars().count() + 1;
+            output.push(format!(
+                "{:w$}--> {}:{}",
+                "",
+                first.line + 1,
+                column,
+                w = width
+            ));
+            output.push(format!("{:w$} |", "", w = width));
+        }
+
+        let mut previous: Option<usize> = None;
+        for segment in &segments {
+            let (_, text) = lines[segment.line];
+
+            if previous != Some(segment.line) {
+                if previous.is_some_and(|previous| previous + 1 < segment.line) {
+                    output.push("...".to_string());
+                }
+                output.push(format!("{:>w$} | {}", segment.line + 1, text, w = width));
+                previous = Some(segment.line);
+            }
+
+            let label = &self.labels[segment.label];
+            let marker = if label.primary { '^' } else { '-' };
+            let mut underline: String = text[..segment.start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = text[segment.start..segment.end].chars().count().max(1);
+            underline.extend(std::iter::repeat_n(marker, length));
+            if segment.last && !label.message.is_empty() {
+                underline.push(' ');
+                underline.push_str(&label.message);
+            }
+            output.push(format!("{:w$} | {}", "", underline, w = width));
+        }
+
+        for note in &self.notes {
+            output.push(format!("{:w$} = note: {}", "", note, w = width));
+        }
+
+        let mut report = String::new();
+        for line in output {
+            report.push_str(line.trim_end());
+            report.push('\n');
+        }
+        report
+    }
+
+    /// The part of every label on each line, ordered by line and column.
+    fn segments(&self, source: &str, lines: &[(usize, &str)]) -> Vec<Segment> {
+        let mut segments = vec![];
+
+        for (index, label) in self.labels.iter().enumerate() {
+            let span = clamp(source, &label.span);
+            let first = segments.len();
+
+            for (line, &(start, text)) in lines.iter().enumerate() {
+                let end = start + text.len();
+                let touches = if span.is_empty() {
+                    (start..=end).contains(&span.start)
+                } else {
+                    span.start <= end && start < span.end
+                };
+
+                if touches {
+                    let from = span.start.max(start);
+                    segments.push(Segment {
+                        line,
+                        start: from - start,
+                        end: span.end.clamp(from, end) - start,
+                        label: index,
+                        last: false,
+                    });
+                }
+            }
+
+            if segments.len() > first {
+                segments.last_mut().unwrap().last = true;
+            }
+        }
+
+        segments.sort_by_key(|segment| (segment.line, segment.start, segment.label));
+        segments
+    }
+}
+
+/// Only the first line of the report, e.g. `error: division by zero`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// The part of a label on one line. `start` and `end` are byte offsets
+/// into the line.
+struct Segment {
+    line: usize,
+    start: usize,
+    end: usize,
+    label: usize,
+    /// Whether this is the label's last line, where its message goes.
+    last: bool,
+}
+
+/// Every line of `source` with the offset it starts at, without the line
+/// break. A source ending in a line break has an empty last line, so that
+/// an error at the very end has somewhere to go.
+fn lines(source: &str) -> Vec<(usize, &str)> {
+    let mut start = 0;
+
+    source
+        .split('\n')
+        .map(|line| {
+            let line_start = start;
+            start += line.len() + 1;
+            (line_start, line)
+        })
+        .collect()
+}
+
+/// Keep `span` inside `source`, widened to whole characters.
+fn clamp(source: &str, span: &Span) -> Span {
+    let mut start = span.start.min(source.len());
+    while !source.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = span.end.clamp(start, source.len());
+    while !source.is_char_boundary(end) {
+        end += 1;
+    }
+
+    start..end
+}
+
+#[test]
+fn test_single_label() {
+    let diagnostic = Diagnostic::error("unknown symbol `?`")
+        .with_label(4..5, "not a number, operator or variable");
+
+    assert_eq!(
+        diagnostic.render("1 2 ? +"),
+        "\
+error: unknown symbol `?`
+ --> 1:5
+  |
+1 | 1 2 ? +
+  |     ^ not a number, operator or variable
+"
+    );
+    assert_eq!(diagnostic.to_string(), "error: unknown symbol `?`");
+}
+
+#[test]
+fn test_labels_on_the_same_line() {
+    let diagnostic = Diagnostic::error("division by zero")
+        .with_label(6..7, "divides by zero")
+        .with_secondary_label(2..5, "this is zero")
+        .with_note("use `%` for the remainder");
+
+    assert_eq!(
+        diagnostic.render("7 0 0 / 1 +"),
+        "\
+error: division by zero
+ --> 1:7
+  |
+1 | 7 0 0 / 1 +
+  |   --- this is zero
+  |       ^ divides by zero
+  = note: use `%` for the remainder
+"
+    );
+}
+
+#[test]
+fn test_labels_over_several_lines() {
+    let source = "begin\n  1 +\n  dup\n\n\n  10 >\nthen";
+    let diagnostic = Diagnostic::warning("loop never ends")
+        .with_label(0..14, "this loop")
+        .with_secondary_label(22..26, "is never true");
+
+    assert_eq!(
+        diagnostic.render(source),
+        "\
+warning: loop never ends
+ --> 1:1
+  |
+1 | begin
+  | ^^^^^
+2 |   1 +
+  | ^^^^^
+3 |   dup
+  | ^^ this loop
+...
+6 |   10 >
+  |   ---- is never true
+"
+    );
+}
+
+#[test]
+fn test_gutter_fits_the_largest_line_number() {
+    let source = "x\n".repeat(9) + "y\nz";
+    let diagnostic = Diagnostic::note("here").with_label(18..21, "");
+
+    assert_eq!(
+        diagnostic.render(&source),
+        "\
+note: here
+  --> 10:1
+   |
+10 | y
+   | ^
+11 | z
+   | ^
+"
+    );
+}
+
+#[test]
+fn test_spans_at_and_past_the_end() {
+    assert_eq!(
+        Diagnostic::error("unexpected end of input")
+            .with_label(3..3, "expected an operand")
+            .render("1 +"),
+        "\
+error: unexpected end of input
+ --> 1:4
+  |
+1 | 1 +
+  |    ^ expected an operand
+"
+    );
+    assert_eq!(
+        Diagnostic::error("cut short")
+            .with_label(2..100, "")
+            .render("1 +\n"),
+        "\
+error: cut short
+ --> 1:3
+  |
+1 | 1 +
+  |   ^
+"
+    );
+}
+
+#[test]
+fn test_columns_count_characters_and_keep_tabs() {
+    assert_eq!(
+        Diagnostic::error("unknown symbol `é`")
+            .with_label(6..7, "")
+            .render("\t\"é\" é"),
+        "\
+error: unknown symbol `é`
+ --> 1:6
+  |
+1 | \t\"é\" é
+  | \t    ^
+"
+    );
+    // A span inside a character covers the whole character.
+    assert_eq!(
+        Diagnostic::error("e").with_label(3..4, "").render("ab·cd"),
+        "error: e\n --> 1:3\n  |\n1 | ab·cd\n  |   ^\n"
+    );
+}
+
+#[test]
+fn test_no_labels() {
+    assert_eq!(
+        Diagnostic::error("nothing to evaluate")
+            .with_note("enter RPN such as `1 2 +`")
+            .render(""),
+        "error: nothing to evaluate\n  = note: enter RPN such as `1 2 +`\n"
+    );
+}
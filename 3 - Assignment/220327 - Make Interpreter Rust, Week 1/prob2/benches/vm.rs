@@ -0,0 +1,47 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use prob2::{compile, evaluate, tokenize, CalculatorInput, Vm};
+
+/// `3x² - 2xy + y / 7`, written with variables for the VM.
+const FORMULA: &str = "3 x @ x @ * * 2 x @ y @ * * - y @ 7 / +";
+
+/// The same formula with `x` and `y` replaced by literals, since
+/// `evaluate` has no way to receive arguments.
+fn substitute(x: i32, y: i32) -> Vec<CalculatorInput> {
+    tokenize(FORMULA)
+        .unwrap()
+        .into_iter()
+        .map(|input| match input {
+            CalculatorInput::Load(name) if name == "x" => CalculatorInput::Value(x),
+            CalculatorInput::Load(name) if name == "y" => CalculatorInput::Value(y),
+            input => input,
+        })
+        .collect()
+}
+
+fn bench_formula(c: &mut Criterion) {
+    let arguments: Vec<(i32, i32)> = (0..1000).map(|i| (i % 97 - 48, i % 31)).collect();
+    let substituted: Vec<Vec<CalculatorInput>> =
+        arguments.iter().map(|&(x, y)| substitute(x, y)).collect();
+    let program = compile(&tokenize(FORMULA).unwrap()).unwrap();
+    let mut vm = Vm::new();
+
+    let mut group = c.benchmark_group("formula x1000");
+    group.bench_function("evaluate", |b| {
+        b.iter(|| {
+            for inputs in &substituted {
+                black_box(evaluate(black_box(inputs)).unwrap());
+            }
+        })
+    });
+    group.bench_function("vm", |b| {
+        b.iter(|| {
+            for &(x, y) in &arguments {
+                black_box(vm.run(black_box(&program), &[x, y]).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_formula);
+criterion_main!(benches);
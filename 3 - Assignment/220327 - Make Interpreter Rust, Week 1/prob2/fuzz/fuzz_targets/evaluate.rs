@@ -0,0 +1,14 @@
+#![no_main]
+
+//! `evaluate_with` returns an error instead of panicking, whatever the
+//! inputs and options.
+
+use libfuzzer_sys::fuzz_target;
+use prob2::{evaluate_with, CalculatorInput, Options};
+
+fuzz_target!(|data: (Vec<CalculatorInput>, Options)| {
+    let (inputs, mut options) = data;
+    // Keep loops that never end from timing out the fuzzer.
+    options.step_limit = options.step_limit.min(10_000);
+    let _ = evaluate_with(&inputs, options);
+});
@@ -0,0 +1,25 @@
+#![no_main]
+
+//! Text that lexes evaluates without panicking, and writes back to text
+//! that lexes to the same inputs.
+
+use libfuzzer_sys::fuzz_target;
+use prob2::{evaluate_with, tokenize, Options};
+
+fuzz_target!(|source: &str| {
+    let Ok(inputs) = tokenize(source) else {
+        return;
+    };
+    let options = Options {
+        step_limit: 10_000,
+        ..Default::default()
+    };
+    let _ = evaluate_with(&inputs, options);
+
+    let text = inputs
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    assert_eq!(tokenize(&text).as_ref(), Ok(&inputs), "{}", text);
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+//! The VM gives the same result as `evaluate` on every program that
+//! compiles.
+
+use libfuzzer_sys::fuzz_target;
+use prob2::{compile, evaluate_with, CalculatorInput, EvalError, Options, Vm};
+
+const STEP_LIMIT: usize = 10_000;
+
+fuzz_target!(|inputs: Vec<CalculatorInput>| {
+    // Variables that are read before they are written become parameters,
+    // which `evaluate` has no way to receive.
+    let Ok(program) = compile(&inputs) else {
+        return;
+    };
+    if !program.parameters().is_empty() {
+        return;
+    }
+    let options = Options {
+        step_limit: STEP_LIMIT,
+        ..Default::default()
+    };
+    let expected = evaluate_with(&inputs, options);
+    // `then` and `begin` compile to no instruction, so the VM may still
+    // finish a program that ran out of steps in `evaluate`.
+    if expected == Err(EvalError::StepLimitExceeded) {
+        return;
+    }
+    let actual = Vm::with_step_limit(STEP_LIMIT).run(&program, &[]);
+    assert_eq!(actual, expected);
+});
@@ -0,0 +1,37 @@
+//! Evaluate one RPN program, given as arguments or on standard input, and
+//! print its result, or a report pointing at where it went wrong.
+//!
+//! ```text
+//! $ cargo run --bin calc -- 4 8 + 7 0 /
+//! error: division by zero
+//!  --> 1:11
+//!   |
+//! 1 | 4 8 + 7 0 /
+//!   |           ^ the divisor is 0
+//! ```
+
+use prob2::evaluate_source;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+fn main() -> io::Result<ExitCode> {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let source = if arguments.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        source
+    } else {
+        arguments.join(" ")
+    };
+
+    match evaluate_source(&source) {
+        Ok(value) => {
+            println!("{}", value);
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(diagnostic) => {
+            eprint!("{}", diagnostic.render(&source));
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use prob2::Interpreter;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+Enter RPN such as `4 8 + 7 5 - /`. The stack is kept between lines.
+
+  : name ... ;   define a word, e.g. `: square dup * ;`
+  x !  x @       store into and load from the variable x
+  c if ... else ... then
+                 run the first branch if c is not zero
+  begin ... c until
+                 repeat the body until c is not zero
+  :stack         show the stack
+  :undo          undo the last line that changed the session
+  :clear         start a new session
+  :help          show this message";
+
+/// An interpreter plus the sessions it replaced, for `:undo`.
+#[derive(Default)]
+struct Repl {
+    interpreter: Interpreter,
+    history: Vec<Interpreter>,
+}
+
+impl Repl {
+    /// Handle one line of input and return the text to print.
+    fn handle(&mut self, line: &str) -> String {
+        match line.trim() {
+            "" | ":stack" => self.show_stack(),
+            ":help" => HELP.to_string(),
+            ":undo" => match self.history.pop() {
+                Some(previous) => {
+                    self.interpreter = previous;
+                    self.show_stack()
+                }
+                None => "nothing to undo".to_string(),
+            },
+            ":clear" => {
+                let previous = std::mem::take(&mut self.interpreter);
+                self.history.push(previous);
+                self.show_stack()
+            }
+            // `: name ... ;` is a definition, not a command.
+            command
+                if command
+                    .strip_prefix(':')
+                    .is_some_and(|name| name.starts_with(|c: char| c.is_ascii_alphabetic())) =>
+            {
+                format!("unknown command `{}`, try :help", command)
+            }
+            source => {
+                let previous = self.interpreter.clone();
+                match self.interpreter.eval(source) {
+                    Ok(()) => {
+                        self.history.push(previous);
+                        self.show_stack()
+                    }
+                    Err(error) => format!("error: {}\n{}", error, self.show_stack()),
+                }
+            }
+        }
+    }
+
+    fn show_stack(&self) -> String {
+        let values: Vec<String> = self
+            .interpreter
+            .stack()
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+
+        format!("[{}]", values.join(" "))
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut repl = Repl::default();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    print!("> ");
+    stdout.flush()?;
+
+    for line in stdin.lock().lines() {
+        println!("{}", repl.handle(&line?));
+        print!("> ");
+        stdout.flush()?;
+    }
+
+    println!();
+    Ok(())
+}
+
+#[test]
+fn test_stack_is_shown_after_each_line() {
+    let mut repl = Repl::default();
+    assert_eq!(repl.handle("1 2"), "[1 2]");
+    assert_eq!(repl.handle("3 +"), "[1 5]");
+    assert_eq!(repl.handle(":stack"), "[1 5]");
+}
+
+#[test]
+fn test_errors_keep_the_session() {
+    let mut repl = Repl::default();
+    repl.handle("1 0");
+    assert_eq!(repl.handle("/"), "error: division by zero\n[1 0]");
+    assert_eq!(repl.handle("1 ?"), "error: unknown symbol `?` at 2\n[1 0]");
+}
+
+#[test]
+fn test_undo() {
+    let mut repl = Repl::default();
+    repl.handle(": double 2 * ;");
+    repl.handle("5 double");
+    repl.handle("bad");
+    assert_eq!(repl.handle(":undo"), "[]");
+    assert_eq!(repl.handle("1 double"), "[2]");
+    assert_eq!(repl.handle(":undo"), "[]");
+    assert_eq!(repl.handle(":undo"), "[]");
+    assert_eq!(repl.handle(":undo"), "nothing to undo");
+    assert!(repl.handle("1 double").starts_with("error: unknown symbol"));
+}
+
+#[test]
+fn test_clear_can_be_undone() {
+    let mut repl = Repl::default();
+    repl.handle("3 x !");
+    repl.handle("x @");
+    assert_eq!(repl.handle(":clear"), "[]");
+    assert!(repl.handle("x @").starts_with("error: unbound variable"));
+    assert_eq!(repl.handle(":undo"), "[3]");
+}
+
+#[test]
+fn test_unknown_command() {
+    let mut repl = Repl::default();
+    assert_eq!(repl.handle(":quit"), "unknown command `:quit`, try :help");
+    assert!(repl.handle(":help").contains(":undo"));
+}
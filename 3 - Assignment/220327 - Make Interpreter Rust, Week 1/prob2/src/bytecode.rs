@@ -0,0 +1,439 @@
+use crate::control::jump_targets;
+use crate::{tokenize, CalculatorInput, EvalError, LexError};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Instruction codes. `Push`, `Load`, `Store` and the jumps are followed by
+/// a four byte little-endian operand: an index into the constant pool for
+/// `Push`, a variable slot for `Load` and `Store`, and a byte offset into
+/// the code for the jumps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub(crate) enum Opcode {
+    Push,
+    Load,
+    Store,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    Negate,
+    Abs,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Min,
+    Max,
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    Clear,
+    Jump,
+    JumpIfZero,
+}
+
+/// Every opcode, indexed by its byte.
+const OPCODES: &[Opcode] = &[
+    Opcode::Push,
+    Opcode::Load,
+    Opcode::Store,
+    Opcode::Add,
+    Opcode::Subtract,
+    Opcode::Multiply,
+    Opcode::Divide,
+    Opcode::Modulo,
+    Opcode::Power,
+    Opcode::Negate,
+    Opcode::Abs,
+    Opcode::And,
+    Opcode::Or,
+    Opcode::Xor,
+    Opcode::Shl,
+    Opcode::Shr,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::Less,
+    Opcode::LessEqual,
+    Opcode::Greater,
+    Opcode::GreaterEqual,
+    Opcode::Min,
+    Opcode::Max,
+    Opcode::Dup,
+    Opcode::Drop,
+    Opcode::Swap,
+    Opcode::Over,
+    Opcode::Rot,
+    Opcode::Clear,
+    Opcode::Jump,
+    Opcode::JumpIfZero,
+];
+
+pub(crate) const OPERAND_LEN: usize = 4;
+
+impl Opcode {
+    pub(crate) fn from_byte(byte: u8) -> Opcode {
+        OPCODES[byte as usize]
+    }
+
+    /// Number of values the instruction needs on the stack.
+    pub(crate) fn arity(self) -> usize {
+        match self {
+            Opcode::Push | Opcode::Load | Opcode::Clear | Opcode::Jump => 0,
+            Opcode::Store | Opcode::Negate | Opcode::Abs | Opcode::Dup | Opcode::Drop => 1,
+            Opcode::JumpIfZero => 1,
+            Opcode::Rot => 3,
+            _ => 2,
+        }
+    }
+
+    /// The opcode an input compiles to. `then` and `begin` only mark where
+    /// jumps land, so they compile to nothing.
+    fn for_input(input: &CalculatorInput) -> Option<Opcode> {
+        use CalculatorInput::*;
+
+        let opcode = match input {
+            Add => Opcode::Add,
+            Subtract => Opcode::Subtract,
+            Multiply => Opcode::Multiply,
+            Divide => Opcode::Divide,
+            Modulo => Opcode::Modulo,
+            Power => Opcode::Power,
+            Negate => Opcode::Negate,
+            Abs => Opcode::Abs,
+            And => Opcode::And,
+            Or => Opcode::Or,
+            Xor => Opcode::Xor,
+            Shl => Opcode::Shl,
+            Shr => Opcode::Shr,
+            Equal => Opcode::Equal,
+            NotEqual => Opcode::NotEqual,
+            Less => Opcode::Less,
+            LessEqual => Opcode::LessEqual,
+            Greater => Opcode::Greater,
+            GreaterEqual => Opcode::GreaterEqual,
+            Min => Opcode::Min,
+            Max => Opcode::Max,
+            Dup => Opcode::Dup,
+            Drop => Opcode::Drop,
+            Swap => Opcode::Swap,
+            Over => Opcode::Over,
+            Rot => Opcode::Rot,
+            Clear => Opcode::Clear,
+            Store(_) => Opcode::Store,
+            Load(_) => Opcode::Load,
+            Value(_) => Opcode::Push,
+            If | Until => Opcode::JumpIfZero,
+            Else => Opcode::Jump,
+            Then | Begin => return None,
+        };
+
+        Some(opcode)
+    }
+}
+
+/// Calculator inputs compiled to bytecode for `Vm::run`.
+///
+/// The inputs are kept as well, so a program can be written back out as
+/// canonical RPN text with `to_string` and read again with `parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub(crate) inputs: Vec<CalculatorInput>,
+    pub(crate) code: Vec<u8>,
+    pub(crate) constants: Vec<i32>,
+    /// Byte offset of the instruction compiled from each input.
+    pub(crate) offsets: Vec<usize>,
+    /// Name of the variable in each slot. The first `parameters` slots are
+    /// read before they are written, so their values are the arguments
+    /// given to `Vm::run`.
+    pub(crate) variables: Vec<String>,
+    pub(crate) parameters: usize,
+}
+
+impl Program {
+    /// The inputs the program was compiled from.
+    pub fn inputs(&self) -> &[CalculatorInput] {
+        &self.inputs
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn constants(&self) -> &[i32] {
+        &self.constants
+    }
+
+    /// Variables the program reads before writing, in the order their
+    /// values are expected by `Vm::run`.
+    pub fn parameters(&self) -> &[String] {
+        &self.variables[..self.parameters]
+    }
+
+    /// Index of the input compiled to the instruction at `offset`.
+    pub(crate) fn input_at(&self, offset: usize) -> usize {
+        self.offsets.partition_point(|&start| start <= offset) - 1
+    }
+}
+
+/// The inputs separated by single spaces, e.g. `4 8 + 7 5 - /`.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut inputs = self.inputs.iter();
+        if let Some(first) = inputs.next() {
+            write!(f, "{}", first)?;
+        }
+        for input in inputs {
+            write!(f, " {}", input)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProgramError {
+    Lex(LexError),
+    Eval(EvalError),
+}
+
+impl From<LexError> for ProgramError {
+    fn from(error: LexError) -> Self {
+        ProgramError::Lex(error)
+    }
+}
+
+impl From<EvalError> for ProgramError {
+    fn from(error: EvalError) -> Self {
+        ProgramError::Eval(error)
+    }
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramError::Lex(error) => error.fmt(f),
+            ProgramError::Eval(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+/// Tokenize and compile RPN text.
+impl FromStr for Program {
+    type Err = ProgramError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Ok(compile(&tokenize(source)?)?)
+    }
+}
+
+/// Programs are stored as their RPN text, so a config file can hold
+/// `formula = "4 8 + 7 5 - /"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Program {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Program {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        source.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Compile calculator inputs to bytecode. Equal values share one entry of
+/// the constant pool, and every variable gets a numbered slot. Fails if a
+/// control word has no partner, like `evaluate` does before running.
+pub fn compile(inputs: &[CalculatorInput]) -> Result<Program, EvalError> {
+    let jumps = jump_targets(inputs.iter().map(CalculatorInput::control))
+        .map_err(|at| EvalError::UnmatchedControl { at })?;
+
+    let mut variables = vec![];
+    let mut slots = HashMap::new();
+
+    // Variables read before they are written are parameters, and get the
+    // first slots.
+    let mut written = HashSet::new();
+    for input in inputs {
+        match input {
+            CalculatorInput::Load(name) if !written.contains(name) => {
+                slot(&mut slots, &mut variables, name);
+            }
+            CalculatorInput::Store(name) => {
+                written.insert(name);
+            }
+            _ => {}
+        }
+    }
+    let parameters = variables.len();
+
+    let mut code = vec![];
+    let mut constants = vec![];
+    let mut pool: HashMap<i32, u32> = HashMap::new();
+    let mut offsets = Vec::with_capacity(inputs.len());
+    // Jump operands to fill in once every input has an offset, as the
+    // position of the operand and the input the jump lands on.
+    let mut patches = vec![];
+
+    for (at, input) in inputs.iter().enumerate() {
+        offsets.push(code.len());
+        match Opcode::for_input(input) {
+            Some(opcode) => code.push(opcode as u8),
+            None => continue,
+        }
+
+        let operand = match input {
+            CalculatorInput::Value(n) => *pool.entry(*n).or_insert_with(|| {
+                constants.push(*n);
+                constants.len() as u32 - 1
+            }),
+            CalculatorInput::Load(name) | CalculatorInput::Store(name) => {
+                slot(&mut slots, &mut variables, name)
+            }
+            CalculatorInput::If | CalculatorInput::Else | CalculatorInput::Until => {
+                patches.push((code.len(), jumps[at]));
+                0
+            }
+            _ => continue,
+        };
+        code.extend_from_slice(&operand.to_le_bytes());
+    }
+
+    for (position, target) in patches {
+        let offset = offsets.get(target).copied().unwrap_or(code.len()) as u32;
+        code[position..position + OPERAND_LEN].copy_from_slice(&offset.to_le_bytes());
+    }
+
+    Ok(Program {
+        inputs: inputs.to_vec(),
+        code,
+        constants,
+        offsets,
+        variables,
+        parameters,
+    })
+}
+
+fn slot<'a>(slots: &mut HashMap<&'a str, u32>, variables: &mut Vec<String>, name: &'a str) -> u32 {
+    *slots.entry(name).or_insert_with(|| {
+        variables.push(name.to_string());
+        variables.len() as u32 - 1
+    })
+}
+
+#[test]
+fn test_opcode_table_matches_bytes() {
+    for (byte, opcode) in OPCODES.iter().enumerate() {
+        assert_eq!(*opcode as usize, byte);
+    }
+}
+
+#[test]
+fn test_compile_shares_constants() {
+    let program = compile(&crate::tokenize("7 7 * 3 -").unwrap()).unwrap();
+    let push = |index: u8| [Opcode::Push as u8, index, 0, 0, 0];
+    let code = [
+        &push(0)[..],
+        &push(0),
+        &[Opcode::Multiply as u8],
+        &push(1),
+        &[Opcode::Subtract as u8],
+    ]
+    .concat();
+
+    assert_eq!(program.constants(), &[7, 3]);
+    assert_eq!(program.code(), code);
+    assert_eq!(program.input_at(10), 2);
+    assert_eq!(program.input_at(13), 3);
+}
+
+#[test]
+fn test_compile_finds_parameters() {
+    let program = compile(&crate::tokenize("1 t ! x @ t @ + y @ * x ! x @").unwrap()).unwrap();
+
+    assert_eq!(program.parameters(), &["x".to_string(), "y".to_string()]);
+    assert_eq!(program.variables, vec!["x", "y", "t"]);
+}
+
+#[test]
+fn test_compile_jumps() {
+    let program = compile(&crate::tokenize("1 if 2 else 3 then begin 4 until").unwrap()).unwrap();
+    let push = |index: u8| [Opcode::Push as u8, index, 0, 0, 0];
+    let jump = |opcode: Opcode, offset: u8| [opcode as u8, offset, 0, 0, 0];
+    let code = [
+        &push(0)[..],
+        &jump(Opcode::JumpIfZero, 20),
+        &push(1),
+        &jump(Opcode::Jump, 25),
+        &push(2),
+        &push(3),
+        &jump(Opcode::JumpIfZero, 25),
+    ]
+    .concat();
+
+    assert_eq!(program.code(), code);
+    assert_eq!(program.input_at(25), 7);
+    assert_eq!(
+        compile(&crate::tokenize("1 if 2").unwrap()),
+        Err(EvalError::UnmatchedControl { at: 1 })
+    );
+}
+
+#[test]
+fn test_program_text_round_trips() {
+    for source in [
+        "4 8 + 7 5 - /",
+        "-3 neg abs 2 ** 17 % 1 4 << | 12 10 ^ & 5 >> 3 min 4 max",
+        "1 2 == 3 4 != 5 6 < 7 8 <= 9 10 > 11 12 >= dup drop swap over rot clear",
+        "6 x ! x @ x @ * 0 > if 1 else 2 then begin 1 until",
+    ] {
+        let program: Program = source.parse().unwrap();
+        assert_eq!(program.to_string(), source);
+        assert_eq!(program.to_string().parse(), Ok(program));
+    }
+
+    let program: Program = "  4\t8 +\n".parse().unwrap();
+    assert_eq!(program.to_string(), "4 8 +");
+}
+
+#[test]
+fn test_program_parse_errors() {
+    assert_eq!(
+        "1 ? +".parse::<Program>(),
+        Err(ProgramError::Lex(LexError::UnknownSymbol {
+            symbol: "?".to_string(),
+            span: 2..3,
+        }))
+    );
+    assert_eq!(
+        "1 if 2".parse::<Program>(),
+        Err(ProgramError::Eval(EvalError::UnmatchedControl { at: 1 }))
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_program_serde_uses_text() {
+    let program: Program = "4 8 + 7 5 - /".parse().unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+
+    assert_eq!(json, r#""4 8 + 7 5 - /""#);
+    assert_eq!(serde_json::from_str::<Program>(&json).unwrap(), program);
+    assert!(serde_json::from_str::<Program>(r#""1 +?""#).is_err());
+}
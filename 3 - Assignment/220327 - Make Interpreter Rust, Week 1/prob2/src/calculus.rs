@@ -0,0 +1,359 @@
+use crate::expression::{Expression, Statement};
+use crate::operation::Operation;
+use crate::{CalculatorInput, InfixError};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum DeriveError {
+    Infix(InfixError),
+    NotDifferentiable { operator: CalculatorInput },
+}
+
+impl From<InfixError> for DeriveError {
+    fn from(error: InfixError) -> Self {
+        DeriveError::Infix(error)
+    }
+}
+
+impl fmt::Display for DeriveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeriveError::Infix(error) => error.fmt(f),
+            DeriveError::NotDifferentiable { operator } => {
+                write!(f, "cannot differentiate `{}`", operator)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeriveError {}
+
+/// Differentiate the value a program leaves on the stack with respect to
+/// the variable `var`, and return the simplified derivative as a new
+/// program. Variables stored by the program are replaced by their
+/// definitions, and every other variable is treated as a constant.
+///
+/// Everything stays in integers, so `/` is `NotDifferentiable`: the
+/// derivative of `x / 2` is one half, which truncating division would
+/// turn into 0.
+pub fn derive(inputs: &[CalculatorInput], var: &str) -> Result<Vec<CalculatorInput>, DeriveError> {
+    let derivative = formula(inputs)?.derive(var)?;
+
+    let mut output = vec![];
+    derivative.compile(&mut output);
+    Ok(output)
+}
+
+/// Rewrite a program as a single expression with constants folded and
+/// identities such as `x + 0` and `x * 1` removed.
+///
+/// Unlike `optimize`, this treats the program as a formula: a part that
+/// could only fail, like `(1 / 0) * 0`, may be simplified away.
+pub fn simplify(inputs: &[CalculatorInput]) -> Result<Vec<CalculatorInput>, InfixError> {
+    let mut output = vec![];
+    formula(inputs)?.simplify().compile(&mut output);
+    Ok(output)
+}
+
+/// The value a program leaves on the stack, with every stored variable
+/// replaced by the expression it was assigned.
+fn formula(inputs: &[CalculatorInput]) -> Result<Expression, InfixError> {
+    let mut definitions = HashMap::new();
+
+    for statement in Statement::from_postfix(inputs)? {
+        match statement {
+            Statement::Assign(name, value) => {
+                let value = value.substitute(&definitions);
+                definitions.insert(name, value);
+            }
+            Statement::Expression(expression) => return Ok(expression.substitute(&definitions)),
+        }
+    }
+
+    Err(InfixError::EmptyStack)
+}
+
+impl Expression {
+    fn substitute(self, definitions: &HashMap<String, Expression>) -> Expression {
+        match self {
+            Expression::Variable(name) => match definitions.get(&name) {
+                Some(value) => value.clone(),
+                None => Expression::Variable(name),
+            },
+            Expression::Unary(operator, operand) => {
+                Expression::unary(operator, operand.substitute(definitions))
+            }
+            Expression::Binary(operator, lhs, rhs) => Expression::binary(
+                operator,
+                lhs.substitute(definitions),
+                rhs.substitute(definitions),
+            ),
+            value => value,
+        }
+    }
+
+    fn contains(&self, var: &str) -> bool {
+        match self {
+            Expression::Value(_) => false,
+            Expression::Variable(name) => name == var,
+            Expression::Unary(_, operand) => operand.contains(var),
+            Expression::Binary(_, lhs, rhs) => lhs.contains(var) || rhs.contains(var),
+        }
+    }
+
+    pub(crate) fn simplify(&self) -> Expression {
+        match self {
+            Expression::Unary(operator, operand) => unary(operator.clone(), operand.simplify()),
+            Expression::Binary(operator, lhs, rhs) => {
+                binary(operator.clone(), lhs.simplify(), rhs.simplify())
+            }
+            leaf => leaf.clone(),
+        }
+    }
+
+    pub(crate) fn derive(&self, var: &str) -> Result<Expression, DeriveError> {
+        use CalculatorInput::{
+            Abs, Add, Equal, Greater, GreaterEqual, Less, LessEqual, Max, Min, Modulo, Multiply,
+            Negate, NotEqual, Power, Subtract,
+        };
+
+        if !self.contains(var) {
+            return Ok(Expression::Value(0));
+        }
+
+        let derivative = match self {
+            Expression::Value(_) => Expression::Value(0),
+            Expression::Variable(_) => Expression::Value(1),
+
+            Expression::Unary(Negate, f) => unary(Negate, f.derive(var)?),
+            Expression::Unary(Abs, f) => {
+                let f = f.simplify();
+                let sign = binary(
+                    Subtract,
+                    binary(Greater, f.clone(), Expression::Value(0)),
+                    binary(Less, f.clone(), Expression::Value(0)),
+                );
+                binary(Multiply, sign, f.derive(var)?)
+            }
+
+            Expression::Binary(operator @ (Add | Subtract), f, g) => {
+                binary(operator.clone(), f.derive(var)?, g.derive(var)?)
+            }
+            Expression::Binary(Multiply, f, g) => {
+                let (f, g) = (f.simplify(), g.simplify());
+                binary(
+                    Add,
+                    binary(Multiply, f.derive(var)?, g.clone()),
+                    binary(Multiply, f.clone(), g.derive(var)?),
+                )
+            }
+            Expression::Binary(Power, f, n) if !n.contains(var) => {
+                let (f, n) = (f.simplify(), n.simplify());
+                let power = binary(
+                    Power,
+                    f.clone(),
+                    binary(Subtract, n.clone(), Expression::Value(1)),
+                );
+                binary(Multiply, binary(Multiply, n, power), f.derive(var)?)
+            }
+            // Away from the jumps, `f % n` moves with `f`.
+            Expression::Binary(Modulo, f, n) if !n.contains(var) => f.derive(var)?,
+            Expression::Binary(operator @ (Min | Max), f, g) => {
+                let (f, g) = (f.simplify(), g.simplify());
+                // The same operand `evaluate` picks when the two are equal.
+                let (picks_f, picks_g) = match operator {
+                    Min => (LessEqual, Greater),
+                    _ => (GreaterEqual, Less),
+                };
+                binary(
+                    Add,
+                    binary(
+                        Multiply,
+                        binary(picks_f, f.clone(), g.clone()),
+                        f.derive(var)?,
+                    ),
+                    binary(Multiply, binary(picks_g, f, g.clone()), g.derive(var)?),
+                )
+            }
+            // Comparisons are flat everywhere except where they jump.
+            Expression::Binary(
+                Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual,
+                _,
+                _,
+            ) => Expression::Value(0),
+
+            Expression::Unary(operator, _) | Expression::Binary(operator, _, _) => {
+                return Err(DeriveError::NotDifferentiable {
+                    operator: operator.clone(),
+                })
+            }
+        };
+
+        Ok(derivative)
+    }
+}
+
+/// Build `operator operand`, folding it if the operand is a constant.
+fn unary(operator: CalculatorInput, operand: Expression) -> Expression {
+    if let (Operation::Unary(f), Expression::Value(n)) = (operator.operation(), &operand) {
+        if let Ok(result) = f(*n, &Default::default()) {
+            return Expression::Value(result);
+        }
+    }
+
+    match (operator, operand) {
+        (CalculatorInput::Negate, Expression::Unary(CalculatorInput::Negate, operand)) => *operand,
+        (operator, operand) => Expression::unary(operator, operand),
+    }
+}
+
+/// Build `lhs operator rhs`, folding constants and removing identities.
+fn binary(operator: CalculatorInput, lhs: Expression, rhs: Expression) -> Expression {
+    use CalculatorInput::{Add, Divide, Multiply, Negate, Power, Subtract};
+    use Expression::Value;
+
+    if let (Operation::Binary(f), Value(a), Value(b)) = (operator.operation(), &lhs, &rhs) {
+        if let Ok(result) = f(*a, *b, &Default::default()) {
+            return Value(result);
+        }
+    }
+
+    match (operator, lhs, rhs) {
+        (Add, Value(0), e) | (Add, e, Value(0)) => e,
+        (Add, a, b) if a == b => binary(Multiply, Value(2), a),
+        (Add, a, Expression::Unary(Negate, b)) => binary(Subtract, a, *b),
+
+        (Subtract, e, Value(0)) => e,
+        (Subtract, Value(0), e) => unary(Negate, e),
+        (Subtract, a, b) if a == b => Value(0),
+        (Subtract, a, Expression::Unary(Negate, b)) => binary(Add, a, *b),
+
+        (Multiply, Value(0), _) | (Multiply, _, Value(0)) => Value(0),
+        (Multiply, Value(1), e) | (Multiply, e, Value(1)) => e,
+        (Multiply, Value(-1), e) | (Multiply, e, Value(-1)) => unary(Negate, e),
+
+        (Divide, e, Value(1)) => e,
+
+        (Power, _, Value(0)) => Value(1),
+        (Power, e, Value(1)) => e,
+
+        (operator, lhs, rhs) => Expression::binary(operator, lhs, rhs),
+    }
+}
+
+#[cfg(test)]
+fn rpn(source: &str) -> Vec<CalculatorInput> {
+    crate::tokenize(source).unwrap()
+}
+
+/// Evaluate `program` with the variable `x` set to `x`.
+#[cfg(test)]
+fn at(program: &[CalculatorInput], x: i32) -> Result<i32, crate::EvalError> {
+    let mut inputs = vec![
+        CalculatorInput::Value(x),
+        CalculatorInput::Store("x".to_string()),
+    ];
+    inputs.extend_from_slice(program);
+    crate::evaluate(&inputs)
+}
+
+#[test]
+fn test_derive_simple_rules() {
+    assert_eq!(derive(&rpn("7"), "x"), Ok(rpn("0")));
+    assert_eq!(derive(&rpn("x @"), "x"), Ok(rpn("1")));
+    assert_eq!(derive(&rpn("y @"), "x"), Ok(rpn("0")));
+    assert_eq!(derive(&rpn("x @ x @ *"), "x"), Ok(rpn("2 x @ *")));
+    assert_eq!(derive(&rpn("3 x @ * 5 +"), "x"), Ok(rpn("3")));
+    assert_eq!(derive(&rpn("x @ neg"), "x"), Ok(rpn("-1")));
+    assert_eq!(derive(&rpn("x @ 3 **"), "x"), Ok(rpn("3 x @ 2 ** *")));
+    assert_eq!(derive(&rpn("x @ y @ *"), "y"), Ok(rpn("x @")));
+}
+
+#[test]
+fn test_derive_polynomial_matches_hand_derivative() {
+    // 3x³ - 2x² + 7x - 4, whose derivative is 9x² - 4x + 7.
+    let polynomial = rpn("3 x @ 3 ** * 2 x @ 2 ** * - 7 x @ * + 4 -");
+    let derivative = derive(&polynomial, "x").unwrap();
+
+    for x in -10..=10 {
+        assert_eq!(at(&derivative, x), Ok(9 * x * x - 4 * x + 7), "x = {}", x);
+    }
+}
+
+#[test]
+fn test_derive_through_stored_variables() {
+    // v = at + v0 with a = 3 and v0 = 2, and s = vt, written here with
+    // t as `x`.
+    let program = rpn("3 a ! 2 v0 ! a @ x @ * v0 @ + v ! v @ x @ *");
+    let derivative = derive(&program, "x").unwrap();
+
+    for x in -5..=5 {
+        assert_eq!(at(&derivative, x), Ok(6 * x + 2), "x = {}", x);
+    }
+}
+
+#[test]
+fn test_derive_piecewise_operators() {
+    let abs = derive(&rpn("x @ 3 - abs"), "x").unwrap();
+    assert_eq!(at(&abs, 1), Ok(-1));
+    assert_eq!(at(&abs, 5), Ok(1));
+
+    let max = derive(&rpn("x @ x @ * 4 x @ * max"), "x").unwrap();
+    assert_eq!(at(&max, 3), Ok(4));
+    assert_eq!(at(&max, 4), Ok(8));
+    assert_eq!(at(&max, 5), Ok(10));
+
+    assert_eq!(derive(&rpn("x @ 2 <"), "x"), Ok(rpn("0")));
+    assert_eq!(derive(&rpn("x @ 5 %"), "x"), Ok(rpn("1")));
+}
+
+#[test]
+fn test_derive_errors() {
+    assert_eq!(
+        derive(&rpn("x @ 1 &"), "x"),
+        Err(DeriveError::NotDifferentiable {
+            operator: CalculatorInput::And
+        })
+    );
+    assert_eq!(
+        derive(&rpn("2 x @ **"), "x"),
+        Err(DeriveError::NotDifferentiable {
+            operator: CalculatorInput::Power
+        })
+    );
+    assert_eq!(
+        derive(&rpn("x @ 2 /"), "x"),
+        Err(DeriveError::NotDifferentiable {
+            operator: CalculatorInput::Divide
+        })
+    );
+    assert_eq!(derive(&rpn("y @ 2 /"), "x"), Ok(rpn("0")));
+    assert_eq!(
+        derive(&rpn("1 +"), "x"),
+        Err(DeriveError::Infix(InfixError::StackUnderflow { at: 1 }))
+    );
+    assert_eq!(
+        derive(&rpn("1 x !"), "x"),
+        Err(DeriveError::Infix(InfixError::EmptyStack))
+    );
+    assert_eq!(derive(&rpn("y @ 1 &"), "x"), Ok(rpn("0")));
+}
+
+#[test]
+fn test_simplify() {
+    assert_eq!(simplify(&rpn("x @ 0 + 1 *")), Ok(rpn("x @")));
+    assert_eq!(simplify(&rpn("2 3 + x @ *")), Ok(rpn("5 x @ *")));
+    assert_eq!(simplify(&rpn("x @ y @ - x @ y @ - -")), Ok(rpn("0")));
+    assert_eq!(simplify(&rpn("x @ y @ neg -")), Ok(rpn("x @ y @ +")));
+    assert_eq!(
+        simplify(&rpn("x @ neg neg 0 y @ - +")),
+        Ok(rpn("x @ y @ -"))
+    );
+    assert_eq!(simplify(&rpn("2 t ! t @ t @ * x @ +")), Ok(rpn("4 x @ +")));
+    assert_eq!(simplify(&rpn("x @ 0 ** 1 0 / +")), Ok(rpn("1 1 0 / +")));
+    assert_eq!(
+        simplify(&rpn("1 x ! x @ 2 x ! 10 +")),
+        Err(InfixError::StoreWithPendingOperands { at: 4 })
+    );
+}
@@ -0,0 +1,201 @@
+use crate::operation::Operation;
+use crate::{handle_input, pop_value_from_stack, CalculatorInput, Environment, EvalError};
+use crate::{Numeric, Options};
+
+/// Inputs that decide which input runs next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Control {
+    If,
+    Else,
+    Then,
+    Begin,
+    Until,
+}
+
+impl<N> CalculatorInput<N> {
+    pub(crate) fn control(&self) -> Option<Control> {
+        match self {
+            CalculatorInput::If => Some(Control::If),
+            CalculatorInput::Else => Some(Control::Else),
+            CalculatorInput::Then => Some(Control::Then),
+            CalculatorInput::Begin => Some(Control::Begin),
+            CalculatorInput::Until => Some(Control::Until),
+            _ => None,
+        }
+    }
+}
+
+/// Pair every `if` with its `else` and `then`, and every `until` with its
+/// `begin`. Returns, for every input, where to continue when it jumps:
+/// past the `else` or `then` for `if`, past the `then` for `else`, and
+/// past the `begin` for `until`. Other inputs get the next index.
+///
+/// Fails with the index of the first input that has no partner.
+pub(crate) fn jump_targets(
+    controls: impl IntoIterator<Item = Option<Control>>,
+) -> Result<Vec<usize>, usize> {
+    let mut targets = vec![];
+    let mut open: Vec<(Control, usize)> = vec![];
+
+    for (at, control) in controls.into_iter().enumerate() {
+        targets.push(at + 1);
+
+        match control {
+            Some(control @ (Control::If | Control::Begin)) => open.push((control, at)),
+            Some(Control::Else) => match open.pop() {
+                Some((Control::If, start)) => {
+                    targets[start] = at + 1;
+                    open.push((Control::Else, at));
+                }
+                _ => return Err(at),
+            },
+            Some(Control::Then) => match open.pop() {
+                Some((Control::If | Control::Else, start)) => targets[start] = at + 1,
+                _ => return Err(at),
+            },
+            Some(Control::Until) => match open.pop() {
+                Some((Control::Begin, start)) => targets[at] = start + 1,
+                _ => return Err(at),
+            },
+            None => {}
+        }
+    }
+
+    match open.first() {
+        Some(&(_, at)) => Err(at),
+        None => Ok(targets),
+    }
+}
+
+/// Run the input at `pc` and return the index of the input to run next.
+/// `if` and `until` pop a condition and jump when it is zero. Errors
+/// report `at`, which is `pc` unless the input was reached through a call.
+pub(crate) fn step<N: Numeric>(
+    stack: &mut Vec<CalculatorInput<N>>,
+    environment: &mut Environment<N>,
+    options: &Options,
+    jumps: &[usize],
+    pc: usize,
+    at: usize,
+    input: &CalculatorInput<N>,
+) -> Result<usize, EvalError<N>> {
+    match input.operation() {
+        Operation::Control(Control::If | Control::Until) => {
+            let condition = pop_value_from_stack(stack).ok_or(EvalError::StackUnderflow { at })?;
+            if condition == N::from_bool(false) {
+                Ok(jumps[pc])
+            } else {
+                Ok(pc + 1)
+            }
+        }
+        Operation::Control(Control::Else) => Ok(jumps[pc]),
+        Operation::Control(_) => Ok(pc + 1),
+        _ => {
+            handle_input(stack, environment, options, at, input)?;
+            Ok(pc + 1)
+        }
+    }
+}
+
+/// Counts the inputs run against `Options::step_limit`.
+pub(crate) struct Steps {
+    remaining: usize,
+}
+
+impl Steps {
+    pub(crate) fn new(limit: usize) -> Self {
+        Steps { remaining: limit }
+    }
+
+    pub(crate) fn take<N>(&mut self) -> Result<(), EvalError<N>> {
+        self.remaining = self
+            .remaining
+            .checked_sub(1)
+            .ok_or(EvalError::StepLimitExceeded)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn evaluate(source: &str) -> Result<i32, EvalError> {
+    crate::evaluate(&crate::tokenize(source).unwrap())
+}
+
+#[test]
+fn test_jump_targets() {
+    let inputs = crate::tokenize("1 if 2 else 3 then begin 4 until").unwrap();
+    let targets = jump_targets(inputs.iter().map(CalculatorInput::control));
+
+    assert_eq!(targets, Ok(vec![1, 4, 3, 6, 5, 6, 7, 8, 7]));
+}
+
+#[test]
+fn test_unmatched_control() {
+    assert_eq!(
+        evaluate("1 then"),
+        Err(EvalError::UnmatchedControl { at: 1 })
+    );
+    assert_eq!(
+        evaluate("1 if 2 else 3 else 4 then"),
+        Err(EvalError::UnmatchedControl { at: 5 })
+    );
+    assert_eq!(
+        evaluate("begin 1 if until then"),
+        Err(EvalError::UnmatchedControl { at: 3 })
+    );
+    assert_eq!(
+        evaluate("1 if begin 2"),
+        Err(EvalError::UnmatchedControl { at: 1 })
+    );
+    assert_eq!(
+        evaluate("1 0 / until"),
+        Err(EvalError::UnmatchedControl { at: 3 })
+    );
+}
+
+#[test]
+fn test_if_else_then() {
+    assert_eq!(evaluate("1 if 10 else 20 then"), Ok(10));
+    assert_eq!(evaluate("0 if 10 else 20 then"), Ok(20));
+    assert_eq!(evaluate("5 -1 if 1 + then"), Ok(6));
+    assert_eq!(evaluate("5 0 if 1 + then"), Ok(5));
+    assert_eq!(
+        evaluate("3 dup 0 < if neg else dup 0 == if drop 7 then then"),
+        Ok(3)
+    );
+    assert_eq!(
+        evaluate("if 1 then"),
+        Err(EvalError::StackUnderflow { at: 0 })
+    );
+}
+
+#[test]
+fn test_begin_until() {
+    // 5! with n and the product on the stack.
+    assert_eq!(
+        evaluate("5 1 begin over * swap 1 - swap over 1 <= until swap drop"),
+        Ok(120)
+    );
+    // The body runs once even when the condition already holds.
+    assert_eq!(evaluate("0 begin 1 + 1 until"), Ok(1));
+    assert_eq!(
+        evaluate("1 n ! begin n @ 2 * n ! n @ 1000 > until n @"),
+        Ok(1024)
+    );
+}
+
+#[test]
+fn test_runaway_loop_hits_step_limit() {
+    assert_eq!(evaluate("begin 0 until"), Err(EvalError::StepLimitExceeded));
+
+    let inputs = crate::tokenize("0 begin 1 + dup 10 == until").unwrap();
+    let options = |step_limit| Options {
+        step_limit,
+        ..Default::default()
+    };
+    assert_eq!(crate::evaluate_with(&inputs, options(62)), Ok(10));
+    assert_eq!(
+        crate::evaluate_with(&inputs, options(61)),
+        Err(EvalError::StepLimitExceeded)
+    );
+}
@@ -0,0 +1,213 @@
+use crate::notation::InfixError;
+use crate::operation::Operation;
+use crate::parser::{function_name, infix_operator_for, Associativity, PREFIX_PRECEDENCE};
+use crate::CalculatorInput;
+
+/// Tree form of a calculator program, used by the infix front-end.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expression {
+    Value(i32),
+    Variable(String),
+    Unary(CalculatorInput, Box<Expression>),
+    Binary(CalculatorInput, Box<Expression>, Box<Expression>),
+}
+
+/// One `;` separated part of an infix program.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Statement {
+    Assign(String, Expression),
+    Expression(Expression),
+}
+
+/// Operands that never need parentheses.
+const ATOM_PRECEDENCE: u8 = u8::MAX;
+
+impl Expression {
+    pub(crate) fn unary(operator: CalculatorInput, operand: Expression) -> Self {
+        Expression::Unary(operator, Box::new(operand))
+    }
+
+    pub(crate) fn binary(operator: CalculatorInput, lhs: Expression, rhs: Expression) -> Self {
+        Expression::Binary(operator, Box::new(lhs), Box::new(rhs))
+    }
+
+    /// Append the postfix (RPN) form of this expression to `output`.
+    pub(crate) fn compile(&self, output: &mut Vec<CalculatorInput>) {
+        match self {
+            Expression::Value(n) => output.push(CalculatorInput::Value(*n)),
+            Expression::Variable(name) => output.push(CalculatorInput::Load(name.clone())),
+            Expression::Unary(operator, operand) => {
+                operand.compile(output);
+                output.push(operator.clone());
+            }
+            Expression::Binary(operator, lhs, rhs) => {
+                lhs.compile(output);
+                rhs.compile(output);
+                output.push(operator.clone());
+            }
+        }
+    }
+
+    /// Render as infix text with only the parentheses the parser needs.
+    pub(crate) fn to_infix(&self) -> String {
+        self.render().0
+    }
+
+    fn render(&self) -> (String, u8) {
+        match self {
+            Expression::Value(n) if *n < 0 => (n.to_string(), PREFIX_PRECEDENCE),
+            Expression::Value(n) => (n.to_string(), ATOM_PRECEDENCE),
+            Expression::Variable(name) => (name.clone(), ATOM_PRECEDENCE),
+            Expression::Unary(CalculatorInput::Negate, operand) => {
+                let (text, precedence) = operand.render();
+                // A bare literal would be read back as a negative literal.
+                let needs_parentheses = precedence < PREFIX_PRECEDENCE
+                    || matches!(**operand, Expression::Value(n) if n >= 0);
+                let text = if text.starts_with('-') && !needs_parentheses {
+                    format!("- {}", text)
+                } else {
+                    format!("-{}", parenthesize(text, needs_parentheses))
+                };
+
+                (text, PREFIX_PRECEDENCE)
+            }
+            Expression::Unary(function, operand) => {
+                let name = function_name(function).expect("every unary input has a call form");
+                (format!("{}({})", name, operand.to_infix()), ATOM_PRECEDENCE)
+            }
+            Expression::Binary(function, lhs, rhs) if infix_operator_for(function).is_none() => {
+                let name = function_name(function).expect("every binary input has a call form");
+                let text = format!("{}({}, {})", name, lhs.to_infix(), rhs.to_infix());
+                (text, ATOM_PRECEDENCE)
+            }
+            Expression::Binary(operator, lhs, rhs) => {
+                let operator = infix_operator_for(operator).unwrap();
+                let precedence = operator.precedence;
+
+                let (lhs, lhs_precedence) = lhs.render();
+                let (rhs, rhs_precedence) = rhs.render();
+
+                let lhs_needs_parentheses = lhs_precedence < precedence
+                    || (lhs_precedence == precedence
+                        && operator.associativity == Associativity::Right);
+                // A prefix expression is read as a single operand on the right.
+                let rhs_needs_parentheses = rhs_precedence != PREFIX_PRECEDENCE
+                    && (rhs_precedence < precedence
+                        || (rhs_precedence == precedence
+                            && operator.associativity == Associativity::Left));
+
+                let text = format!(
+                    "{} {} {}",
+                    parenthesize(lhs, lhs_needs_parentheses),
+                    operator.symbol,
+                    parenthesize(rhs, rhs_needs_parentheses),
+                );
+
+                (text, precedence)
+            }
+        }
+    }
+}
+
+impl Statement {
+    /// Rebuild statements from postfix inputs by running them on a stack of
+    /// expressions instead of a stack of numbers. Every store becomes an
+    /// assignment, and the value left on the stack becomes the last
+    /// statement.
+    ///
+    /// Only numbers may be dropped or cleared: an expression could fail
+    /// when evaluated, and leaving it out would hide that. A store must
+    /// leave the stack empty, since its assignment runs before the
+    /// operands still on the stack, which may load the same variable.
+    pub(crate) fn from_postfix(inputs: &[CalculatorInput]) -> Result<Vec<Self>, InfixError> {
+        let mut statements = vec![];
+        let mut stack: Vec<Expression> = vec![];
+
+        for (at, input) in inputs.iter().enumerate() {
+            if stack.len() < input.arity() {
+                return Err(InfixError::StackUnderflow { at });
+            }
+
+            let expression = match input.operation() {
+                Operation::Push(n) => Expression::Value(n),
+                Operation::Load(name) => Expression::Variable(name.to_string()),
+                Operation::Unary(_) => Expression::unary(input.clone(), stack.pop().unwrap()),
+                Operation::Binary(_) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    Expression::binary(input.clone(), lhs, rhs)
+                }
+                Operation::Shuffle { pops, pushes } => {
+                    let operands = stack.split_off(stack.len() - pops);
+                    let discards_expression =
+                        operands.iter().enumerate().any(|(index, operand)| {
+                            !pushes.contains(&index) && !matches!(operand, Expression::Value(_))
+                        });
+                    if discards_expression {
+                        return Err(InfixError::DiscardedExpression { at });
+                    }
+                    stack.extend(pushes.iter().map(|&index| operands[index].clone()));
+                    continue;
+                }
+                Operation::Clear => {
+                    if stack
+                        .iter()
+                        .any(|operand| !matches!(operand, Expression::Value(_)))
+                    {
+                        return Err(InfixError::DiscardedExpression { at });
+                    }
+                    stack.clear();
+                    continue;
+                }
+                Operation::Store(_) if stack.len() > 1 => {
+                    return Err(InfixError::StoreWithPendingOperands { at });
+                }
+                Operation::Store(name) => {
+                    let value = stack.pop().unwrap();
+                    statements.push(Statement::Assign(name.to_string(), value));
+                    continue;
+                }
+                Operation::Control(_) => return Err(InfixError::NotAnExpression { at }),
+            };
+            stack.push(expression);
+        }
+
+        match stack.len() {
+            0 if inputs.is_empty() => Err(InfixError::EmptyInput),
+            0 if statements.is_empty() => Err(InfixError::EmptyStack),
+            0 => Ok(statements),
+            1 => {
+                statements.push(Statement::Expression(stack.pop().unwrap()));
+                Ok(statements)
+            }
+            _ => Err(InfixError::LeftoverOperands(
+                stack.iter().map(Expression::to_infix).collect(),
+            )),
+        }
+    }
+
+    pub(crate) fn compile(&self, output: &mut Vec<CalculatorInput>) {
+        match self {
+            Statement::Assign(name, value) => {
+                value.compile(output);
+                output.push(CalculatorInput::Store(name.clone()));
+            }
+            Statement::Expression(expression) => expression.compile(output),
+        }
+    }
+
+    pub(crate) fn to_infix(&self) -> String {
+        match self {
+            Statement::Assign(name, value) => format!("{} = {}", name, value.to_infix()),
+            Statement::Expression(expression) => expression.to_infix(),
+        }
+    }
+}
+
+fn parenthesize(text: String, needed: bool) -> String {
+    if needed {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
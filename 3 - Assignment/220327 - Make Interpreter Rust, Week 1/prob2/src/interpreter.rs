@@ -0,0 +1,507 @@
+use crate::control::{jump_targets, step, Control, Steps};
+use crate::lexer::{looks_like_number, parse_word, variable_access, words};
+use crate::{
+    parse_infix, CalculatorInput, Environment, EvalError, LexError, Options, ParseError, Span,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+
+#[derive(Debug, PartialEq)]
+pub enum InterpreterError {
+    Lex(LexError),
+    Parse(ParseError),
+    Eval(EvalError),
+    InvalidDefinition { span: Span },
+    RecursiveDefinition { word: String, span: Span },
+    UnmatchedControl { span: Span },
+}
+
+impl From<LexError> for InterpreterError {
+    fn from(error: LexError) -> Self {
+        InterpreterError::Lex(error)
+    }
+}
+
+impl From<ParseError> for InterpreterError {
+    fn from(error: ParseError) -> Self {
+        InterpreterError::Parse(error)
+    }
+}
+
+impl From<EvalError> for InterpreterError {
+    fn from(error: EvalError) -> Self {
+        InterpreterError::Eval(error)
+    }
+}
+
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpreterError::Lex(error) => error.fmt(f),
+            InterpreterError::Parse(error) => error.fmt(f),
+            InterpreterError::Eval(error) => error.fmt(f),
+            InterpreterError::InvalidDefinition { span } => {
+                write!(f, "invalid definition at {}", span.start)
+            }
+            InterpreterError::RecursiveDefinition { word, .. } => {
+                write!(f, "`{}` cannot be used before it is defined", word)
+            }
+            InterpreterError::UnmatchedControl { span } => {
+                write!(f, "unmatched control word at {}", span.start)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
+#[derive(Clone)]
+enum Instruction {
+    Input(CalculatorInput),
+    Call(usize),
+}
+
+impl Instruction {
+    fn control(&self) -> Option<Control> {
+        match self {
+            Instruction::Input(input) => input.control(),
+            Instruction::Call(_) => None,
+        }
+    }
+}
+
+/// A line or a definition body, with its jumps resolved. Control words
+/// must be paired inside the same body.
+#[derive(Clone)]
+struct Code {
+    instructions: Vec<Instruction>,
+    jumps: Vec<usize>,
+}
+
+impl Code {
+    /// Fails with the index of the first unpaired control word.
+    fn new(instructions: Vec<Instruction>) -> Result<Self, usize> {
+        let jumps = jump_targets(instructions.iter().map(Instruction::control))?;
+        Ok(Code {
+            instructions,
+            jumps,
+        })
+    }
+}
+
+/// An RPN session whose stack and variables survive between calls to
+/// `eval`, and which understands user-defined words such as
+/// `: square dup * ;`.
+#[derive(Clone, Default)]
+pub struct Interpreter {
+    stack: Vec<CalculatorInput>,
+    environment: Environment,
+    words: HashMap<String, usize>,
+    definitions: Vec<Code>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Run one line of source. On error the session is left exactly as it
+    /// was before the call.
+    pub fn eval(&mut self, source: &str) -> Result<(), InterpreterError> {
+        let definitions = self.definitions.len();
+        let mut replaced = vec![];
+
+        let result = self.transaction(|interpreter| {
+            let code = interpreter.compile(source, &mut replaced)?;
+            interpreter.run(&code)
+        });
+
+        if result.is_err() {
+            self.definitions.truncate(definitions);
+            for (name, previous) in replaced.into_iter().rev() {
+                match previous {
+                    Some(definition) => self.words.insert(name, definition),
+                    None => self.words.remove(&name),
+                };
+            }
+        }
+
+        result
+    }
+
+    /// Run one line of infix source such as `x = 4 * 2; x + 1`, sharing the
+    /// stack and variables with `eval`.
+    pub fn eval_infix(&mut self, source: &str) -> Result<(), InterpreterError> {
+        self.transaction(|interpreter| {
+            let instructions = parse_infix(source)?
+                .into_iter()
+                .map(Instruction::Input)
+                .collect();
+            let code = Code::new(instructions).expect("infix source has no control words");
+            interpreter.run(&code)
+        })
+    }
+
+    /// Restore the stack and variables if `f` fails.
+    fn transaction(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), InterpreterError>,
+    ) -> Result<(), InterpreterError> {
+        let stack = self.stack.clone();
+        let environment = self.environment.clone();
+
+        let result = f(self);
+
+        if result.is_err() {
+            self.stack = stack;
+            self.environment = environment;
+        }
+
+        result
+    }
+
+    pub fn variable(&self, name: &str) -> Option<i32> {
+        self.environment.get(name).copied()
+    }
+
+    pub fn stack(&self) -> Vec<i32> {
+        self.stack
+            .iter()
+            .filter_map(|element| match element {
+                CalculatorInput::Value(v) => Some(*v),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Compile a line, recording in `replaced` the previous meaning of
+    /// every word it defines.
+    fn compile(
+        &mut self,
+        source: &str,
+        replaced: &mut Vec<(String, Option<usize>)>,
+    ) -> Result<Code, InterpreterError> {
+        let mut instructions = vec![];
+        let mut spans = vec![];
+        let mut words = words(source).peekable();
+
+        while let Some((word, span)) = words.next() {
+            if word == ":" {
+                let (name, previous) = self.define(span, &mut words)?;
+                replaced.push((name, previous));
+            } else {
+                instructions.push(self.compile_word(word, span.clone(), &mut words)?);
+                spans.push(span);
+            }
+        }
+
+        Code::new(instructions).map_err(|at| InterpreterError::UnmatchedControl {
+            span: spans[at].clone(),
+        })
+    }
+
+    /// Compile `: name body ;`. Words in the body are resolved now, so
+    /// redefining one of them later does not change this definition.
+    fn define<'a>(
+        &mut self,
+        colon: Span,
+        words: &mut Peekable<impl Iterator<Item = (&'a str, Span)>>,
+    ) -> Result<(String, Option<usize>), InterpreterError> {
+        let name = match words.next() {
+            Some((name, _)) if name != ";" && name != ":" && !looks_like_number(name) => name,
+            Some((_, span)) => return Err(InterpreterError::InvalidDefinition { span }),
+            None => return Err(InterpreterError::InvalidDefinition { span: colon }),
+        };
+
+        let mut body = vec![];
+        let mut spans = vec![];
+        loop {
+            match words.next() {
+                Some((";", _)) => break,
+                Some((":", span)) => return Err(InterpreterError::InvalidDefinition { span }),
+                Some((word, span)) if word == name && !self.words.contains_key(word) => {
+                    return Err(InterpreterError::RecursiveDefinition {
+                        word: word.to_string(),
+                        span,
+                    });
+                }
+                Some((word, span)) => {
+                    body.push(self.compile_word(word, span.clone(), words)?);
+                    spans.push(span);
+                }
+                None => return Err(InterpreterError::InvalidDefinition { span: colon }),
+            }
+        }
+
+        let body = Code::new(body).map_err(|at| InterpreterError::UnmatchedControl {
+            span: spans[at].clone(),
+        })?;
+        self.definitions.push(body);
+        let previous = self
+            .words
+            .insert(name.to_string(), self.definitions.len() - 1);
+
+        Ok((name.to_string(), previous))
+    }
+
+    fn compile_word<'a>(
+        &self,
+        word: &str,
+        span: Span,
+        words: &mut Peekable<impl Iterator<Item = (&'a str, Span)>>,
+    ) -> Result<Instruction, InterpreterError> {
+        if let Some(access) = variable_access(word, words.peek()) {
+            words.next();
+            return Ok(Instruction::Input(access));
+        }
+
+        match self.words.get(word) {
+            Some(&definition) => Ok(Instruction::Call(definition)),
+            None => Ok(Instruction::Input(parse_word(word, span)?)),
+        }
+    }
+
+    /// Execute compiled code. Calls are followed with an explicit frame
+    /// stack, so deeply nested words cannot overflow the Rust stack. Errors
+    /// report the position of the instruction in the line being run.
+    fn run(&mut self, code: &Code) -> Result<(), InterpreterError> {
+        let options = Options::default();
+        let mut steps = Steps::new(options.step_limit);
+        // The code being run and the index of its current instruction.
+        let mut frames: Vec<(&Code, usize)> = vec![(code, 0)];
+
+        while let Some(&(body, pc)) = frames.last() {
+            let at = frames[0].1;
+
+            match body.instructions.get(pc) {
+                Some(Instruction::Input(input)) => {
+                    steps.take()?;
+                    let next = step(
+                        &mut self.stack,
+                        &mut self.environment,
+                        &options,
+                        &body.jumps,
+                        pc,
+                        at,
+                        input,
+                    )?;
+                    frames.last_mut().unwrap().1 = next;
+                }
+                Some(Instruction::Call(definition)) => {
+                    frames.push((&self.definitions[*definition], 0))
+                }
+                None => {
+                    frames.pop();
+                    if let Some((_, pc)) = frames.last_mut() {
+                        *pc += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_stack_is_kept_between_lines() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("1 2").is_ok());
+    assert!(interpreter.eval("3 +").is_ok());
+    assert_eq!(interpreter.stack(), vec![1, 5]);
+}
+
+#[test]
+fn test_user_defined_word() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval(": square dup * ;").is_ok());
+    assert!(interpreter.eval("7 square").is_ok());
+    assert_eq!(interpreter.stack(), vec![49]);
+}
+
+#[test]
+fn test_words_using_other_words() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter
+        .eval(": square dup * ; : fourth square square ; 3 fourth")
+        .is_ok());
+    assert_eq!(interpreter.stack(), vec![81]);
+}
+
+#[test]
+fn test_builtin_operators_can_be_redefined() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval(": + * ; 3 4 +").is_ok());
+    assert_eq!(interpreter.stack(), vec![12]);
+}
+
+#[test]
+fn test_definitions_capture_words_at_definition_time() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval(": double 2 * ;").is_ok());
+    assert!(interpreter.eval(": quadruple double double ;").is_ok());
+    assert!(interpreter.eval(": double 3 * ;").is_ok());
+    assert!(interpreter.eval(": * + ;").is_ok());
+    assert!(interpreter.eval("1 quadruple 1 double").is_ok());
+    assert_eq!(interpreter.stack(), vec![4, 3]);
+}
+
+#[test]
+fn test_word_can_extend_its_previous_definition() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter
+        .eval(": inc 1 + ; : inc inc inc ; 0 inc")
+        .is_ok());
+    assert_eq!(interpreter.stack(), vec![2]);
+}
+
+#[test]
+fn test_recursive_definition_is_an_error() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval(": loop 1 loop ;"),
+        Err(InterpreterError::RecursiveDefinition {
+            word: "loop".to_string(),
+            span: 9..13,
+        })
+    );
+    assert_eq!(
+        interpreter.eval("loop"),
+        Err(InterpreterError::Lex(LexError::UnknownSymbol {
+            symbol: "loop".to_string(),
+            span: 0..4,
+        }))
+    );
+}
+
+#[test]
+fn test_invalid_definitions() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval(": 1 2 ;"),
+        Err(InterpreterError::InvalidDefinition { span: 2..3 })
+    );
+    assert_eq!(
+        interpreter.eval(": foo 1"),
+        Err(InterpreterError::InvalidDefinition { span: 0..1 })
+    );
+    assert_eq!(
+        interpreter.eval(": foo : bar ; ;"),
+        Err(InterpreterError::InvalidDefinition { span: 6..7 })
+    );
+}
+
+#[test]
+fn test_failed_line_leaves_session_untouched() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("1 2").is_ok());
+    assert_eq!(
+        interpreter.eval(": foo 5 ; foo + + +"),
+        Err(InterpreterError::Eval(EvalError::StackUnderflow { at: 3 }))
+    );
+    assert_eq!(interpreter.stack(), vec![1, 2]);
+    assert!(interpreter.eval("foo").is_err());
+}
+
+#[test]
+fn test_deeply_nested_words() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval(": w0 1 + ;").is_ok());
+    for i in 1..10_000 {
+        let line = format!(": w{} w{} ;", i, i - 1);
+        assert!(interpreter.eval(&line).is_ok());
+    }
+    assert!(interpreter.eval("0 w9999").is_ok());
+    assert_eq!(interpreter.stack(), vec![1]);
+}
+
+#[test]
+fn test_variables_persist_between_lines() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("10 rate !").is_ok());
+    assert!(interpreter.eval("rate @ 3 *").is_ok());
+    assert_eq!(interpreter.stack(), vec![30]);
+    assert_eq!(interpreter.variable("rate"), Some(10));
+}
+
+#[test]
+fn test_words_can_use_variables() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("0 n ! : bump n @ 1 + n ! ;").is_ok());
+    assert!(interpreter.eval("bump bump bump").is_ok());
+    assert_eq!(interpreter.variable("n"), Some(3));
+}
+
+#[test]
+fn test_unbound_variable_is_reported() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval("x @"),
+        Err(InterpreterError::Eval(EvalError::UnboundVariable(
+            "x".to_string()
+        )))
+    );
+}
+
+#[test]
+fn test_infix_assignment_shares_the_session() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter
+        .eval_infix("width = 4; height = width + 2")
+        .is_ok());
+    assert!(interpreter.eval("width @ height @ *").is_ok());
+    assert_eq!(interpreter.stack(), vec![24]);
+    assert!(interpreter
+        .eval_infix("area = width * height; area / 2")
+        .is_ok());
+    assert_eq!(interpreter.stack(), vec![24, 12]);
+}
+
+#[test]
+fn test_failed_infix_line_keeps_variables() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_infix("x = 1").is_ok());
+    assert!(interpreter.eval_infix("x = 2; x / 0").is_err());
+    assert_eq!(interpreter.variable("x"), Some(1));
+}
+
+#[test]
+fn test_words_with_control_flow() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter
+        .eval(": sign dup 0 < if drop -1 else 0 > if 1 else 0 then then ;")
+        .is_ok());
+    assert!(interpreter
+        .eval(": factorial 1 swap begin swap over * swap 1 - dup 1 <= until drop ;")
+        .is_ok());
+    assert!(interpreter
+        .eval("-7 sign 0 sign 7 sign 5 factorial")
+        .is_ok());
+    assert_eq!(interpreter.stack(), vec![-1, 0, 1, 120]);
+}
+
+#[test]
+fn test_unmatched_control_words() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval(": broken 1 if 2 ;"),
+        Err(InterpreterError::UnmatchedControl { span: 11..13 })
+    );
+    assert_eq!(
+        interpreter.eval("1 2 then"),
+        Err(InterpreterError::UnmatchedControl { span: 4..8 })
+    );
+    assert!(interpreter.eval("broken").is_err());
+}
+
+#[test]
+fn test_runaway_loop_leaves_session_untouched() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("1 2 : forever begin 0 until ;").is_ok());
+    assert_eq!(
+        interpreter.eval("3 forever"),
+        Err(InterpreterError::Eval(EvalError::StepLimitExceeded))
+    );
+    assert_eq!(interpreter.stack(), vec![1, 2]);
+}
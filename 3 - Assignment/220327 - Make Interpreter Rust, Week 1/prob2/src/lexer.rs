@@ -0,0 +1,290 @@
+use crate::{CalculatorInput, Numeric};
+use std::fmt;
+use std::ops::Range;
+
+/// Byte offsets of a token inside the source string.
+pub type Span = Range<usize>;
+
+#[derive(Debug, PartialEq)]
+pub enum LexError {
+    UnknownSymbol { symbol: String, span: Span },
+    InvalidNumber { literal: String, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> &Span {
+        match self {
+            LexError::UnknownSymbol { span, .. } => span,
+            LexError::InvalidNumber { span, .. } => span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnknownSymbol { symbol, span } => {
+                write!(f, "unknown symbol `{}` at {}", symbol, span.start)
+            }
+            LexError::InvalidNumber { literal, span } => {
+                write!(f, "invalid number `{}` at {}", literal, span.start)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Convert whitespace separated RPN text into calculator inputs.
+pub fn tokenize(source: &str) -> Result<Vec<CalculatorInput>, LexError> {
+    let inputs = tokenize_with_spans(source)?
+        .into_iter()
+        .map(|(input, _)| input)
+        .collect();
+
+    Ok(inputs)
+}
+
+/// Same as `tokenize`, but reads literals as any `Numeric` type, for
+/// example `tokenize_as::<Number>("1.5 2/3 +")`.
+pub fn tokenize_as<N: Numeric>(source: &str) -> Result<Vec<CalculatorInput<N>>, LexError> {
+    let inputs = spanned_inputs(source)?
+        .into_iter()
+        .map(|(input, _)| input)
+        .collect();
+
+    Ok(inputs)
+}
+
+/// Same as `tokenize`, but keeps the byte span of every input.
+pub fn tokenize_with_spans(source: &str) -> Result<Vec<(CalculatorInput, Span)>, LexError> {
+    spanned_inputs(source)
+}
+
+fn spanned_inputs<N: Numeric>(source: &str) -> Result<Vec<(CalculatorInput<N>, Span)>, LexError> {
+    let mut inputs = vec![];
+    let mut words = words(source).peekable();
+
+    while let Some((word, span)) = words.next() {
+        if let Some(access) = variable_access(word, words.peek()) {
+            let (_, operator) = words.next().unwrap();
+            inputs.push((access, span.start..operator.end));
+        } else {
+            inputs.push((parse_word(word, span.clone())?, span));
+        }
+    }
+
+    Ok(inputs)
+}
+
+/// Read `name !` as a store and `name @` as a load of a variable.
+pub(crate) fn variable_access<N>(
+    word: &str,
+    next: Option<&(&str, Span)>,
+) -> Option<CalculatorInput<N>> {
+    if !is_identifier(word) {
+        return None;
+    }
+
+    match next {
+        Some(("!", _)) => Some(CalculatorInput::Store(word.to_string())),
+        Some(("@", _)) => Some(CalculatorInput::Load(word.to_string())),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split the source on whitespace, keeping the byte span of every word.
+pub(crate) fn words(source: &str) -> impl Iterator<Item = (&str, Span)> {
+    source.split_whitespace().map(move |word| {
+        let start = word.as_ptr() as usize - source.as_ptr() as usize;
+        (word, start..start + word.len())
+    })
+}
+
+/// RPN spelling of every operator.
+pub(crate) const OPERATOR_WORDS: &[(&str, CalculatorInput)] = &[
+    ("+", CalculatorInput::Add),
+    ("-", CalculatorInput::Subtract),
+    ("*", CalculatorInput::Multiply),
+    ("/", CalculatorInput::Divide),
+    ("%", CalculatorInput::Modulo),
+    ("**", CalculatorInput::Power),
+    ("neg", CalculatorInput::Negate),
+    ("abs", CalculatorInput::Abs),
+    ("&", CalculatorInput::And),
+    ("|", CalculatorInput::Or),
+    ("^", CalculatorInput::Xor),
+    ("<<", CalculatorInput::Shl),
+    (">>", CalculatorInput::Shr),
+    ("==", CalculatorInput::Equal),
+    ("!=", CalculatorInput::NotEqual),
+    ("<", CalculatorInput::Less),
+    ("<=", CalculatorInput::LessEqual),
+    (">", CalculatorInput::Greater),
+    (">=", CalculatorInput::GreaterEqual),
+    ("min", CalculatorInput::Min),
+    ("max", CalculatorInput::Max),
+    ("dup", CalculatorInput::Dup),
+    ("drop", CalculatorInput::Drop),
+    ("swap", CalculatorInput::Swap),
+    ("over", CalculatorInput::Over),
+    ("rot", CalculatorInput::Rot),
+    ("clear", CalculatorInput::Clear),
+    ("if", CalculatorInput::If),
+    ("else", CalculatorInput::Else),
+    ("then", CalculatorInput::Then),
+    ("begin", CalculatorInput::Begin),
+    ("until", CalculatorInput::Until),
+];
+
+pub(crate) fn parse_word<N: Numeric>(
+    word: &str,
+    span: Span,
+) -> Result<CalculatorInput<N>, LexError> {
+    if let Some((_, input)) = OPERATOR_WORDS.iter().find(|(symbol, _)| *symbol == word) {
+        return Ok(input
+            .clone()
+            .map(|_| unreachable!("operator words hold no value")));
+    }
+
+    if looks_like_number(word) {
+        return word
+            .parse()
+            .map(CalculatorInput::Value)
+            .map_err(|_| LexError::InvalidNumber {
+                literal: word.to_string(),
+                span,
+            });
+    }
+
+    Err(LexError::UnknownSymbol {
+        symbol: word.to_string(),
+        span,
+    })
+}
+
+pub(crate) fn looks_like_number(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+}
+
+#[test]
+fn test_tokenize_operators_and_values() {
+    use CalculatorInput::*;
+
+    assert_eq!(
+        tokenize("4 8 + 7 5 - /"),
+        Ok(vec![
+            Value(4),
+            Value(8),
+            Add,
+            Value(7),
+            Value(5),
+            Subtract,
+            Divide
+        ])
+    );
+}
+
+#[test]
+fn test_tokenize_extended_operators() {
+    use CalculatorInput::*;
+
+    assert_eq!(
+        tokenize("% ** neg abs & | ^ << >> == != < <= > >= min max"),
+        Ok(vec![
+            Modulo,
+            Power,
+            Negate,
+            Abs,
+            And,
+            Or,
+            Xor,
+            Shl,
+            Shr,
+            Equal,
+            NotEqual,
+            Less,
+            LessEqual,
+            Greater,
+            GreaterEqual,
+            Min,
+            Max
+        ])
+    );
+}
+
+#[test]
+fn test_tokenize_negative_literal() {
+    use CalculatorInput::*;
+
+    assert_eq!(
+        tokenize("-3 4 - -"),
+        Ok(vec![Value(-3), Value(4), Subtract, Subtract])
+    );
+}
+
+#[test]
+fn test_tokenize_keeps_byte_spans() {
+    let tokens = tokenize_with_spans("  12\t-7 *").unwrap();
+    let spans: Vec<Span> = tokens.into_iter().map(|(_, span)| span).collect();
+
+    assert_eq!(spans, vec![2..4, 5..7, 8..9]);
+}
+
+#[test]
+fn test_tokenize_variable_access() {
+    use CalculatorInput::*;
+
+    let tokens = tokenize_with_spans("3 rate ! rate @").unwrap();
+    assert_eq!(
+        tokens,
+        vec![
+            (Value(3), 0..1),
+            (Store("rate".to_string()), 2..8),
+            (Load("rate".to_string()), 9..15),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_unknown_symbol() {
+    assert_eq!(
+        tokenize("1 2 ?"),
+        Err(LexError::UnknownSymbol {
+            symbol: "?".to_string(),
+            span: 4..5,
+        })
+    );
+}
+
+#[test]
+fn test_tokenize_invalid_number() {
+    assert_eq!(
+        tokenize("1 2x +"),
+        Err(LexError::InvalidNumber {
+            literal: "2x".to_string(),
+            span: 2..4,
+        })
+    );
+}
+
+#[test]
+fn test_tokenize_number_out_of_range() {
+    assert_eq!(
+        tokenize("99999999999"),
+        Err(LexError::InvalidNumber {
+            literal: "99999999999".to_string(),
+            span: 0..11,
+        })
+    );
+}
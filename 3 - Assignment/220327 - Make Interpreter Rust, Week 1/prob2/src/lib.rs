@@ -1,61 +1,337 @@
-#[derive(Debug)]
-pub enum CalculatorInput {
+mod bytecode;
+mod calculus;
+mod control;
+mod expression;
+mod interpreter;
+mod lexer;
+mod notation;
+mod number;
+mod numeric;
+mod operation;
+mod optimize;
+mod parser;
+mod report;
+mod trace;
+mod verify;
+mod vm;
+
+use control::{jump_targets, step, Steps};
+use lexer::OPERATOR_WORDS;
+use operation::Operation;
+use std::collections::HashMap;
+use std::fmt;
+
+pub use bytecode::{compile, Program, ProgramError};
+pub use calculus::{derive, simplify, DeriveError};
+pub use interpreter::{Interpreter, InterpreterError};
+pub use lexer::{tokenize, tokenize_as, tokenize_with_spans, LexError, Span};
+pub use notation::{to_infix, to_rpn, InfixError};
+pub use number::Number;
+pub use numeric::{ArithmeticMode, Division, Numeric, Options};
+pub use optimize::optimize;
+pub use parser::{parse_infix, ParseError};
+pub use report::evaluate_source;
+pub use trace::{evaluate_traced, TraceStep};
+pub use verify::{verify, VerifyError};
+pub use vm::Vm;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum CalculatorInput<N = i32> {
     Add,
     Subtract,
     Multiply,
     Divide,
-    Value(i32),
+    Modulo,
+    Power,
+    Negate,
+    Abs,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Min,
+    Max,
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    Clear,
+    If,
+    Else,
+    Then,
+    Begin,
+    Until,
+    Store(String),
+    Load(String),
+    Value(N),
 }
 
-pub fn evaluate(inputs: &[CalculatorInput]) -> Option<i32> {
-    let mut stack: Vec<CalculatorInput> = vec![];
+impl<N> CalculatorInput<N> {
+    /// Convert the value of a `Value` input, keeping every other input.
+    pub fn map<M>(self, f: impl FnOnce(N) -> M) -> CalculatorInput<M> {
+        use CalculatorInput::*;
 
-    for input in inputs {
-        handle_input(&mut stack, input)?;
+        match self {
+            Add => Add,
+            Subtract => Subtract,
+            Multiply => Multiply,
+            Divide => Divide,
+            Modulo => Modulo,
+            Power => Power,
+            Negate => Negate,
+            Abs => Abs,
+            And => And,
+            Or => Or,
+            Xor => Xor,
+            Shl => Shl,
+            Shr => Shr,
+            Equal => Equal,
+            NotEqual => NotEqual,
+            Less => Less,
+            LessEqual => LessEqual,
+            Greater => Greater,
+            GreaterEqual => GreaterEqual,
+            Min => Min,
+            Max => Max,
+            Dup => Dup,
+            Drop => Drop,
+            Swap => Swap,
+            Over => Over,
+            Rot => Rot,
+            Clear => Clear,
+            If => If,
+            Else => Else,
+            Then => Then,
+            Begin => Begin,
+            Until => Until,
+            Store(name) => Store(name),
+            Load(name) => Load(name),
+            Value(n) => Value(f(n)),
+        }
     }
-
-    get_result_or_none_from_stack(&stack)
 }
 
-fn get_result_or_none_from_stack(stack: &[CalculatorInput]) -> Option<i32> {
-    if let [CalculatorInput::Value(result)] = stack {
-        Some(*result)
-    } else {
-        None
+/// The RPN spelling of the input, as read by `tokenize`: `+`, `neg`,
+/// `x @`, `42` and so on.
+impl<N: Clone + fmt::Display> fmt::Display for CalculatorInput<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculatorInput::Value(n) => write!(f, "{}", n),
+            CalculatorInput::Store(name) => write!(f, "{} !", name),
+            CalculatorInput::Load(name) => write!(f, "{} @", name),
+            operator => {
+                let operator = operator.clone().map(|_| 0);
+                let (word, _) = OPERATOR_WORDS
+                    .iter()
+                    .find(|(_, input)| *input == operator)
+                    .expect("every operator has a word");
+                write!(f, "{}", word)
+            }
+        }
     }
 }
 
-fn handle_input(stack: &mut Vec<CalculatorInput>, input: &CalculatorInput) -> Option<()> {
-    match input {
-        CalculatorInput::Value(n) => stack.push(CalculatorInput::Value(*n)),
-        operator => {
-            let (first, second) = pop_two_elements_from_stack(stack)?;
-            let value = CalculatorInput::Value(calculate(first, second, operator));
-            stack.push(value);
+#[derive(Debug, PartialEq)]
+pub enum EvalError<N = i32> {
+    EmptyInput,
+    EmptyStack,
+    StackUnderflow { at: usize },
+    LeftoverOperands(Vec<N>),
+    DivisionByZero,
+    NegativeExponent,
+    NotAnInteger,
+    Overflow,
+    UnboundVariable(String),
+    ArgumentCount { expected: usize, found: usize },
+    UnmatchedControl { at: usize },
+    StepLimitExceeded,
+}
+
+impl<N: fmt::Display> fmt::Display for EvalError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::EmptyInput => write!(f, "nothing to evaluate"),
+            EvalError::EmptyStack => write!(f, "no result left on the stack"),
+            EvalError::StackUnderflow { at } => {
+                write!(f, "not enough operands for input {}", at + 1)
+            }
+            EvalError::LeftoverOperands(operands) => {
+                write!(f, "{} operands left on the stack:", operands.len())?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::NegativeExponent => write!(f, "negative exponent"),
+            EvalError::NotAnInteger => write!(f, "bitwise operation on a non-integer"),
+            EvalError::Overflow => write!(f, "arithmetic overflow"),
+            EvalError::UnboundVariable(name) => write!(f, "unbound variable `{}`", name),
+            EvalError::ArgumentCount { expected, found } => {
+                write!(f, "expected {} arguments but got {}", expected, found)
+            }
+            EvalError::UnmatchedControl { at } => {
+                write!(f, "unmatched control word at input {}", at + 1)
+            }
+            EvalError::StepLimitExceeded => write!(f, "step limit exceeded"),
         }
     }
+}
+
+impl<N: fmt::Debug + fmt::Display> std::error::Error for EvalError<N> {}
+
+/// Values of the variables written with `Store`.
+pub(crate) type Environment<N = i32> = HashMap<String, N>;
+
+pub fn evaluate<N: Numeric>(inputs: &[CalculatorInput<N>]) -> Result<N, EvalError<N>> {
+    evaluate_with(inputs, Options::default())
+}
 
-    Some(())
+/// Same as `evaluate`, with control over how values are combined, for
+/// example `Division::Exact` to keep `7 2 /` as the fraction `7/2`, or
+/// `ArithmeticMode::Wrapping` to let `i32` results wrap instead of failing
+/// with `Overflow`.
+pub fn evaluate_with<N: Numeric>(
+    inputs: &[CalculatorInput<N>],
+    options: Options,
+) -> Result<N, EvalError<N>> {
+    evaluate_located(inputs, options).map_err(|(error, _)| error)
 }
 
-fn calculate(a: i32, b: i32, operator: &CalculatorInput) -> i32 {
-    match operator {
-        CalculatorInput::Add => a + b,
-        CalculatorInput::Subtract => a - b,
-        CalculatorInput::Multiply => a * b,
-        CalculatorInput::Divide => a / b,
-        _ => panic!("How did you get here?"),
+/// Same as `evaluate_with`, but an error also gives the position of the
+/// input that failed, or `None` if the program as a whole did.
+pub(crate) fn evaluate_located<N: Numeric>(
+    inputs: &[CalculatorInput<N>],
+    options: Options,
+) -> Result<N, (EvalError<N>, Option<usize>)> {
+    if inputs.is_empty() {
+        return Err((EvalError::EmptyInput, None));
     }
+
+    let jumps = jump_targets(inputs.iter().map(CalculatorInput::control))
+        .map_err(|at| (EvalError::UnmatchedControl { at }, Some(at)))?;
+    let mut steps = Steps::new(options.step_limit);
+    let mut stack: Vec<CalculatorInput<N>> = vec![];
+    let mut environment = Environment::new();
+
+    let mut pc = 0;
+    while let Some(input) = inputs.get(pc) {
+        pc = steps
+            .take()
+            .and_then(|()| {
+                step(
+                    &mut stack,
+                    &mut environment,
+                    &options,
+                    &jumps,
+                    pc,
+                    pc,
+                    input,
+                )
+            })
+            .map_err(|error| (error, Some(pc)))?;
+    }
+
+    get_result_from_stack(&stack).map_err(|error| (error, None))
 }
 
-fn pop_two_elements_from_stack(vec: &mut Vec<CalculatorInput>) -> Option<(i32, i32)> {
+fn get_result_from_stack<N: Clone>(stack: &[CalculatorInput<N>]) -> Result<N, EvalError<N>> {
+    match stack {
+        [] => Err(EvalError::EmptyStack),
+        [CalculatorInput::Value(result)] => Ok(result.clone()),
+        operands => Err(EvalError::LeftoverOperands(
+            operands
+                .iter()
+                .filter_map(|operand| match operand {
+                    CalculatorInput::Value(v) => Some(v.clone()),
+                    _ => None,
+                })
+                .collect(),
+        )),
+    }
+}
+
+fn handle_input<N: Numeric>(
+    stack: &mut Vec<CalculatorInput<N>>,
+    environment: &mut Environment<N>,
+    options: &Options,
+    at: usize,
+    input: &CalculatorInput<N>,
+) -> Result<(), EvalError<N>> {
+    let value = match input.operation() {
+        Operation::Push(n) => n,
+        Operation::Unary(operator) => {
+            let a = pop_value_from_stack(stack).ok_or(EvalError::StackUnderflow { at })?;
+            operator(a, options)?
+        }
+        Operation::Binary(operator) => {
+            let (a, b) =
+                pop_two_elements_from_stack(stack).ok_or(EvalError::StackUnderflow { at })?;
+            operator(a, b, options)?
+        }
+        Operation::Shuffle { pops, pushes } => {
+            let operands =
+                pop_elements_from_stack(stack, pops).ok_or(EvalError::StackUnderflow { at })?;
+            stack.extend(
+                pushes
+                    .iter()
+                    .map(|&index| CalculatorInput::Value(operands[index].clone())),
+            );
+            return Ok(());
+        }
+        Operation::Clear => {
+            stack.clear();
+            return Ok(());
+        }
+        Operation::Store(name) => {
+            let value = pop_value_from_stack(stack).ok_or(EvalError::StackUnderflow { at })?;
+            environment.insert(name.to_string(), value);
+            return Ok(());
+        }
+        Operation::Load(name) => environment
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EvalError::UnboundVariable(name.to_string()))?,
+        Operation::Control(_) => unreachable!("control flow is run by `control::step`"),
+    };
+
+    stack.push(CalculatorInput::Value(value));
+
+    Ok(())
+}
+
+fn pop_two_elements_from_stack<N>(vec: &mut Vec<CalculatorInput<N>>) -> Option<(N, N)> {
     let a = pop_value_from_stack(vec)?;
     let b = pop_value_from_stack(vec)?;
 
     Some((b, a))
 }
 
-fn pop_value_from_stack(vec: &mut Vec<CalculatorInput>) -> Option<i32> {
+/// Pop `count` values, returned in the order they were pushed.
+fn pop_elements_from_stack<N>(vec: &mut Vec<CalculatorInput<N>>, count: usize) -> Option<Vec<N>> {
+    if vec.len() < count {
+        return None;
+    }
+
+    let mut elements = (0..count)
+        .map(|_| pop_value_from_stack(vec))
+        .collect::<Option<Vec<N>>>()?;
+    elements.reverse();
+
+    Some(elements)
+}
+
+fn pop_value_from_stack<N>(vec: &mut Vec<CalculatorInput<N>>) -> Option<N> {
     if let CalculatorInput::Value(v) = vec.pop()? {
         Some(v)
     } else {
@@ -65,79 +341,193 @@ fn pop_value_from_stack(vec: &mut Vec<CalculatorInput>) -> Option<i32> {
 
 #[cfg(test)]
 fn calculator_input(s: &str) -> Vec<CalculatorInput> {
-    s.split_whitespace()
-        .map(|s| match s {
-            "+" => CalculatorInput::Add,
-            "-" => CalculatorInput::Subtract,
-            "*" => CalculatorInput::Multiply,
-            "/" => CalculatorInput::Divide,
-            n => CalculatorInput::Value(n.parse().unwrap()),
-        })
-        .collect()
+    tokenize(s).unwrap()
 }
 
 #[test]
-fn test_empty_input_returns_none() {
+fn test_empty_input_returns_error() {
     let input = calculator_input("");
-    assert_eq!(evaluate(&input), None);
+    assert_eq!(evaluate(&input), Err(EvalError::EmptyInput));
 }
 
 #[test]
 fn test_simple_value() {
     let input = calculator_input("10");
-    assert_eq!(evaluate(&input), Some(10));
+    assert_eq!(evaluate(&input), Ok(10));
 }
 
 #[test]
 fn test_simple_addition() {
     let input = calculator_input("2 2 +");
-    assert_eq!(evaluate(&input), Some(4));
+    assert_eq!(evaluate(&input), Ok(4));
 }
 
 #[test]
 fn test_simple_subtraction() {
     let input = calculator_input("7 11 -");
-    assert_eq!(evaluate(&input), Some(-4));
+    assert_eq!(evaluate(&input), Ok(-4));
 }
 
 #[test]
 fn test_simple_multiplication() {
     let input = calculator_input("6 9 *");
-    assert_eq!(evaluate(&input), Some(54));
+    assert_eq!(evaluate(&input), Ok(54));
 }
 
 #[test]
 fn test_simple_division() {
     let input = calculator_input("57 19 /");
-    assert_eq!(evaluate(&input), Some(3));
+    assert_eq!(evaluate(&input), Ok(3));
 }
 
 #[test]
 fn test_complex_operation() {
     let input = calculator_input("4 8 + 7 5 - /");
-    assert_eq!(evaluate(&input), Some(6));
+    assert_eq!(evaluate(&input), Ok(6));
 }
 
 #[test]
-fn test_too_few_operands_returns_none() {
+fn test_too_few_operands_returns_error() {
     let input = calculator_input("2 +");
-    assert_eq!(evaluate(&input), None);
+    assert_eq!(evaluate(&input), Err(EvalError::StackUnderflow { at: 1 }));
 }
 
 #[test]
-fn test_too_many_operands_returns_none() {
+fn test_too_many_operands_returns_error() {
     let input = calculator_input("2 2");
-    assert_eq!(evaluate(&input), None);
+    assert_eq!(
+        evaluate(&input),
+        Err(EvalError::LeftoverOperands(vec![2, 2]))
+    );
 }
 
 #[test]
-fn test_zero_operands_returns_none() {
+fn test_zero_operands_returns_error() {
     let input = calculator_input("+");
-    assert_eq!(evaluate(&input), None);
+    assert_eq!(evaluate(&input), Err(EvalError::StackUnderflow { at: 0 }));
 }
 
 #[test]
-fn test_intermediate_error_returns_none() {
+fn test_intermediate_error_returns_error() {
     let input = calculator_input("+ 2 2 *");
-    assert_eq!(evaluate(&input), None);
+    assert_eq!(evaluate(&input), Err(EvalError::StackUnderflow { at: 0 }));
+}
+
+#[test]
+fn test_underflow_reports_position() {
+    let input = calculator_input("1 2 + * 3");
+    assert_eq!(evaluate(&input), Err(EvalError::StackUnderflow { at: 3 }));
+}
+
+#[test]
+fn test_division_by_zero_returns_error() {
+    let input = calculator_input("1 0 /");
+    assert_eq!(evaluate(&input), Err(EvalError::DivisionByZero));
+}
+
+#[test]
+fn test_overflow_returns_error() {
+    let input = calculator_input("2147483647 1 +");
+    assert_eq!(evaluate(&input), Err(EvalError::Overflow));
+}
+
+#[test]
+fn test_minimum_divided_by_minus_one_returns_error() {
+    let input = calculator_input("-2147483648 -1 /");
+    assert_eq!(evaluate(&input), Err(EvalError::Overflow));
+}
+
+#[cfg(test)]
+fn evaluate_in(s: &str, arithmetic: ArithmeticMode) -> Result<i32, EvalError> {
+    let options = Options {
+        arithmetic,
+        ..Default::default()
+    };
+    evaluate_with(&calculator_input(s), options)
+}
+
+#[test]
+fn test_minimum_divided_by_minus_one_in_each_mode() {
+    let input = "-2147483648 -1 /";
+    assert_eq!(
+        evaluate_in(input, ArithmeticMode::Checked),
+        Err(EvalError::Overflow)
+    );
+    assert_eq!(evaluate_in(input, ArithmeticMode::Wrapping), Ok(i32::MIN));
+    assert_eq!(evaluate_in(input, ArithmeticMode::Saturating), Ok(i32::MAX));
+}
+
+#[test]
+fn test_overflowing_multiplication_in_each_mode() {
+    let input = "65536 -65537 *";
+    assert_eq!(
+        evaluate_in(input, ArithmeticMode::Checked),
+        Err(EvalError::Overflow)
+    );
+    assert_eq!(
+        evaluate_in(input, ArithmeticMode::Wrapping),
+        Ok(65536i32.wrapping_mul(-65537))
+    );
+    assert_eq!(evaluate_in(input, ArithmeticMode::Saturating), Ok(i32::MIN));
+}
+
+#[test]
+fn test_division_by_zero_fails_in_every_mode() {
+    for mode in [ArithmeticMode::Wrapping, ArithmeticMode::Saturating] {
+        assert_eq!(evaluate_in("1 0 /", mode), Err(EvalError::DivisionByZero));
+        assert_eq!(evaluate_in("1 0 %", mode), Err(EvalError::DivisionByZero));
+    }
+}
+
+#[test]
+fn test_wrapping_and_saturating_unary_operators() {
+    let input = "-2147483648 neg -2147483648 abs +";
+    assert_eq!(evaluate_in(input, ArithmeticMode::Wrapping), Ok(0));
+    assert_eq!(evaluate_in(input, ArithmeticMode::Saturating), Ok(i32::MAX));
+    assert_eq!(
+        evaluate_in("-2147483648 -1 %", ArithmeticMode::Wrapping),
+        Ok(0)
+    );
+    assert_eq!(
+        evaluate_in("3 40 **", ArithmeticMode::Saturating),
+        Ok(i32::MAX)
+    );
+}
+
+#[test]
+fn test_store_and_load_variable() {
+    let input = calculator_input("6 x ! x @ x @ *");
+    assert_eq!(evaluate(&input), Ok(36));
+}
+
+#[test]
+fn test_unbound_variable_returns_error() {
+    let input = calculator_input("1 y @ +");
+    assert_eq!(
+        evaluate(&input),
+        Err(EvalError::UnboundVariable("y".to_string()))
+    );
+}
+
+#[test]
+fn test_calculator_input_display_matches_tokenize() {
+    let inputs = calculator_input("-4 x @ y ! neg ** >= clear until");
+    let words: Vec<String> = inputs.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        words,
+        ["-4", "x @", "y !", "neg", "**", ">=", "clear", "until"]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_calculator_input_serde_round_trips() {
+    let inputs = calculator_input("4 x @ + y !");
+    let json = serde_json::to_string(&inputs).unwrap();
+
+    assert_eq!(json, r#"[{"Value":4},{"Load":"x"},"Add",{"Store":"y"}]"#);
+    assert_eq!(
+        serde_json::from_str::<Vec<CalculatorInput>>(&json).unwrap(),
+        inputs
+    );
 }
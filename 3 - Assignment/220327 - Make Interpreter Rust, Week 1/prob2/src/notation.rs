@@ -0,0 +1,508 @@
+use crate::expression::Statement;
+use crate::parser::{
+    binds_literal_first, function, infix_operator, parse_number, scan, unexpected, Associativity,
+    InfixOperator, Token, PREFIX_PRECEDENCE,
+};
+use crate::{CalculatorInput, LexError, ParseError};
+use std::fmt;
+
+/// Entries waiting on the operator stack of the shunting-yard algorithm.
+enum Pending {
+    Binary(&'static InfixOperator),
+    Negation,
+    Parenthesis,
+    Call {
+        name: String,
+        input: &'static CalculatorInput,
+        start: usize,
+        arguments: usize,
+    },
+}
+
+impl Pending {
+    /// Whether this entry must be emitted before `incoming` is pushed.
+    fn pops_before(&self, incoming: &InfixOperator) -> bool {
+        let precedence = match self {
+            Pending::Binary(operator) => operator.precedence,
+            Pending::Negation => PREFIX_PRECEDENCE,
+            Pending::Parenthesis | Pending::Call { .. } => return false,
+        };
+
+        precedence > incoming.precedence
+            || (precedence == incoming.precedence && incoming.associativity == Associativity::Left)
+    }
+
+    fn is_open(&self) -> bool {
+        matches!(self, Pending::Parenthesis | Pending::Call { .. })
+    }
+
+    fn emit(self, output: &mut Vec<CalculatorInput>) {
+        match self {
+            Pending::Binary(operator) => output.push(operator.input.clone()),
+            Pending::Negation => output.push(CalculatorInput::Negate),
+            Pending::Call { input, .. } => output.push(input.clone()),
+            Pending::Parenthesis => {}
+        }
+    }
+}
+
+/// Convert infix text to RPN inputs with Dijkstra's shunting-yard
+/// algorithm. Produces the same inputs as `parse_infix`.
+pub fn to_rpn(source: &str) -> Result<Vec<CalculatorInput>, ParseError> {
+    let tokens = scan(source)?;
+    let mut output = vec![];
+    let mut operators: Vec<Pending> = vec![];
+    let mut store: Option<String> = None;
+    let mut statement_start = true;
+    let mut expect_operand = true;
+    let mut position = 0;
+
+    while let Some((token, span)) = tokens.get(position).cloned() {
+        position += 1;
+        let at_statement_start = statement_start;
+        statement_start = false;
+
+        match token {
+            Token::Identifier(name)
+                if at_statement_start
+                    && matches!(tokens.get(position), Some((Token::Symbol("="), _))) =>
+            {
+                store = Some(name.to_string());
+                position += 1;
+            }
+            Token::Symbol(";") if !expect_operand => {
+                end_statement(&mut operators, &mut output, store.take(), span.start)?;
+                statement_start = true;
+                expect_operand = true;
+            }
+            Token::Identifier(name)
+                if expect_operand
+                    && !matches!(tokens.get(position), Some((Token::Symbol("("), _))) =>
+            {
+                output.push(CalculatorInput::Load(name.to_string()));
+                expect_operand = false;
+            }
+            Token::Number(literal) if expect_operand => {
+                output.push(CalculatorInput::Value(parse_number(literal, false, span)?));
+                expect_operand = false;
+            }
+            Token::Identifier(name) if expect_operand => {
+                let input = function(name).ok_or_else(|| {
+                    ParseError::Lex(LexError::UnknownSymbol {
+                        symbol: name.to_string(),
+                        span: span.clone(),
+                    })
+                })?;
+
+                match tokens.get(position).cloned() {
+                    Some((Token::Symbol("("), _)) => position += 1,
+                    Some((token, span)) => return Err(unexpected(token, span)),
+                    None => return Err(ParseError::UnexpectedEnd { at: source.len() }),
+                }
+
+                operators.push(Pending::Call {
+                    name: name.to_string(),
+                    input,
+                    start: span.start,
+                    arguments: 1,
+                });
+            }
+            Token::Symbol("(") if expect_operand => operators.push(Pending::Parenthesis),
+            Token::Symbol("-") if expect_operand => {
+                if let Some((Token::Number(literal), literal_span)) = tokens.get(position) {
+                    if !binds_literal_first(tokens.get(position + 1)) {
+                        let span = span.start..literal_span.end;
+                        output.push(CalculatorInput::Value(parse_number(literal, true, span)?));
+                        expect_operand = false;
+                        position += 1;
+                        continue;
+                    }
+                }
+
+                operators.push(Pending::Negation);
+            }
+            Token::Symbol(",") if !expect_operand => {
+                emit_until_open(&mut operators, &mut output);
+                match operators.last_mut() {
+                    Some(Pending::Call { arguments, .. }) => *arguments += 1,
+                    _ => return Err(unexpected(token, span)),
+                }
+                expect_operand = true;
+            }
+            Token::Symbol(")") if !expect_operand => {
+                emit_until_open(&mut operators, &mut output);
+                match operators.pop() {
+                    Some(Pending::Call {
+                        name,
+                        input,
+                        start,
+                        arguments,
+                    }) if arguments != input.arity() => {
+                        return Err(ParseError::WrongArgumentCount {
+                            function: name,
+                            expected: input.arity(),
+                            found: arguments,
+                            span: start..span.end,
+                        });
+                    }
+                    Some(call @ Pending::Call { .. }) => call.emit(&mut output),
+                    Some(_) => {}
+                    None => return Err(unexpected(token, span)),
+                }
+            }
+            Token::Symbol(symbol) if !expect_operand => {
+                let operator = infix_operator(symbol).ok_or_else(|| unexpected(token, span))?;
+
+                while operators
+                    .last()
+                    .is_some_and(|pending| pending.pops_before(operator))
+                {
+                    operators.pop().unwrap().emit(&mut output);
+                }
+
+                operators.push(Pending::Binary(operator));
+                expect_operand = true;
+            }
+            _ => return Err(unexpected(token, span)),
+        }
+    }
+
+    if expect_operand {
+        return Err(ParseError::UnexpectedEnd { at: source.len() });
+    }
+
+    end_statement(&mut operators, &mut output, store, source.len())?;
+    Ok(output)
+}
+
+/// Emit every pending operator, then the store of an assignment. An open
+/// parenthesis or call means the statement ended early, at `at`.
+fn end_statement(
+    operators: &mut Vec<Pending>,
+    output: &mut Vec<CalculatorInput>,
+    store: Option<String>,
+    at: usize,
+) -> Result<(), ParseError> {
+    while let Some(pending) = operators.pop() {
+        if pending.is_open() {
+            return Err(ParseError::UnexpectedEnd { at });
+        }
+        pending.emit(output);
+    }
+
+    if let Some(name) = store {
+        output.push(CalculatorInput::Store(name));
+    }
+
+    Ok(())
+}
+
+/// Emit pending operators down to the innermost open parenthesis or call.
+fn emit_until_open(operators: &mut Vec<Pending>, output: &mut Vec<CalculatorInput>) {
+    while operators.last().is_some_and(|pending| !pending.is_open()) {
+        operators.pop().unwrap().emit(output);
+    }
+}
+
+/// Why `to_infix` cannot write a program as infix text. The operands of
+/// `LeftoverOperands` are written as infix.
+#[derive(Debug, PartialEq)]
+pub enum InfixError {
+    EmptyInput,
+    EmptyStack,
+    StackUnderflow { at: usize },
+    LeftoverOperands(Vec<String>),
+    NotAnExpression { at: usize },
+    DiscardedExpression { at: usize },
+    StoreWithPendingOperands { at: usize },
+}
+
+impl fmt::Display for InfixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InfixError::EmptyInput => write!(f, "nothing to convert"),
+            InfixError::EmptyStack => write!(f, "no result left on the stack"),
+            InfixError::StackUnderflow { at } => {
+                write!(f, "not enough operands for input {}", at + 1)
+            }
+            InfixError::LeftoverOperands(operands) => {
+                write!(f, "{} operands left on the stack:", operands.len())?;
+                for (index, operand) in operands.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { "," };
+                    write!(f, "{} `{}`", separator, operand)?;
+                }
+                Ok(())
+            }
+            InfixError::NotAnExpression { at } => {
+                write!(
+                    f,
+                    "input {} is control flow, not part of an expression",
+                    at + 1
+                )
+            }
+            InfixError::DiscardedExpression { at } => {
+                write!(
+                    f,
+                    "input {} discards an expression that is not a number",
+                    at + 1
+                )
+            }
+            InfixError::StoreWithPendingOperands { at } => {
+                write!(
+                    f,
+                    "input {} stores a value while other operands are on the stack",
+                    at + 1
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for InfixError {}
+
+/// Render RPN inputs as infix text, using the minimal set of parentheses
+/// needed to read back the same program.
+///
+/// Not every RPN program has an infix form, so this fails with an
+/// `InfixError` rather than return text that reads back as a different
+/// program:
+///
+/// - a malformed program: `EmptyInput`, `EmptyStack`, `StackUnderflow`
+///   or `LeftoverOperands`, as `evaluate` would also fail;
+/// - `NotAnExpression` for control flow such as `if` and `begin`;
+/// - `DiscardedExpression` when `drop` or `clear` throws away anything but
+///   a number, since the infix form would never run it;
+/// - `StoreWithPendingOperands` for a store while other operands are on
+///   the stack, since the assignment would run before them.
+pub fn to_infix(inputs: &[CalculatorInput]) -> Result<String, InfixError> {
+    let statements: Vec<String> = Statement::from_postfix(inputs)?
+        .iter()
+        .map(Statement::to_infix)
+        .collect();
+
+    Ok(statements.join("; "))
+}
+
+#[cfg(test)]
+fn rpn(source: &str) -> Vec<CalculatorInput> {
+    crate::tokenize(source).unwrap()
+}
+
+#[test]
+fn test_to_rpn_matches_examples() {
+    assert_eq!(to_rpn("2 + 2"), Ok(rpn("2 2 +")));
+    assert_eq!(to_rpn("(4 + 8) / (7 - 5)"), Ok(rpn("4 8 + 7 5 - /")));
+    assert_eq!(to_rpn("1 - 2 - 3"), Ok(rpn("1 2 - 3 -")));
+    assert_eq!(to_rpn("1 - (2 - 3)"), Ok(rpn("1 2 3 - -")));
+}
+
+#[test]
+fn test_to_rpn_unary_minus() {
+    assert_eq!(to_rpn("-5 * -(2 + 1)"), Ok(rpn("-5 2 1 + neg *")));
+}
+
+#[test]
+fn test_to_rpn_agrees_with_pratt_parser() {
+    for source in [
+        "1",
+        "-2147483648",
+        "1 + 2 * 3 - 4 / 5",
+        "((1))",
+        "-(-(3)) * 2",
+        "6 / (2 - 8) * -(4 - 1 - 1)",
+        "2 ** 3 ** 2",
+        "-2 ** 2 + 2 ** -2",
+        "1 + 2 << 3 | 4 & 5 ^ 6 < 7 == 1",
+        "max(1, min(2 + 3, abs(-4))) % 3",
+        "x = 2; y = -x * 3; max(x, y) - abs(y)",
+    ] {
+        assert_eq!(to_rpn(source), crate::parse_infix(source), "{}", source);
+    }
+}
+
+#[test]
+fn test_to_rpn_errors() {
+    assert_eq!(to_rpn("(1 + 2"), Err(ParseError::UnexpectedEnd { at: 6 }));
+    assert_eq!(to_rpn("1 +"), Err(ParseError::UnexpectedEnd { at: 3 }));
+    assert_eq!(
+        to_rpn("1 + 2)"),
+        Err(ParseError::UnexpectedToken {
+            token: ")".to_string(),
+            span: 5..6,
+        })
+    );
+}
+
+#[test]
+fn test_to_infix_uses_minimal_parentheses() {
+    assert_eq!(
+        to_infix(&rpn("4 8 + 7 5 - /")),
+        Ok("(4 + 8) / (7 - 5)".to_string())
+    );
+    assert_eq!(to_infix(&rpn("1 2 3 * +")), Ok("1 + 2 * 3".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 - 3 -")), Ok("1 - 2 - 3".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 3 - -")), Ok("1 - (2 - 3)".to_string()));
+    assert_eq!(to_infix(&rpn("2 -3 -")), Ok("2 - -3".to_string()));
+}
+
+#[test]
+fn test_to_rpn_function_call_errors() {
+    assert_eq!(
+        to_rpn("max(1, 2, 3)"),
+        Err(ParseError::WrongArgumentCount {
+            function: "max".to_string(),
+            expected: 2,
+            found: 3,
+            span: 0..12,
+        })
+    );
+    assert_eq!(
+        to_rpn("1, 2"),
+        Err(ParseError::UnexpectedToken {
+            token: ",".to_string(),
+            span: 1..2,
+        })
+    );
+    assert_eq!(to_rpn("abs(1"), Err(ParseError::UnexpectedEnd { at: 5 }));
+}
+
+#[test]
+fn test_to_infix_extended_operators() {
+    assert_eq!(to_infix(&rpn("2 3 2 ** **")), Ok("2 ** 3 ** 2".to_string()));
+    assert_eq!(
+        to_infix(&rpn("2 3 ** 2 **")),
+        Ok("(2 ** 3) ** 2".to_string())
+    );
+    assert_eq!(to_infix(&rpn("-2 2 **")), Ok("(-2) ** 2".to_string()));
+    assert_eq!(to_infix(&rpn("2 2 ** neg")), Ok("-2 ** 2".to_string()));
+    assert_eq!(to_infix(&rpn("2 neg")), Ok("-(2)".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 + neg")), Ok("-(1 + 2)".to_string()));
+    assert_eq!(
+        to_infix(&rpn("-3 abs 4 max")),
+        Ok("max(abs(-3), 4)".to_string())
+    );
+}
+
+#[test]
+fn test_to_infix_follows_stack_words() {
+    assert_eq!(to_infix(&rpn("3 dup *")), Ok("3 * 3".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 swap -")), Ok("2 - 1".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 3 rot drop +")), Ok("2 + 3".to_string()));
+    assert_eq!(to_infix(&rpn("1 2 over clear 4")), Ok("4".to_string()));
+    assert_eq!(to_infix(&rpn("2 x @ swap drop")), Ok("x".to_string()));
+}
+
+#[test]
+fn test_to_infix_keeps_expressions_that_could_fail() {
+    for (source, at) in [
+        ("1 0 / clear 4", 3),
+        ("1 0 / 5 swap drop", 5),
+        ("y @ drop 3", 1),
+    ] {
+        let inputs = rpn(source);
+        assert!(crate::evaluate(&inputs).is_err(), "{}", source);
+        assert_eq!(
+            to_infix(&inputs),
+            Err(InfixError::DiscardedExpression { at }),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_to_infix_rejects_malformed_programs() {
+    assert_eq!(
+        to_infix(&rpn("1 +")),
+        Err(InfixError::StackUnderflow { at: 1 })
+    );
+    assert_eq!(
+        to_infix(&rpn("1 2")),
+        Err(InfixError::LeftoverOperands(vec![
+            "1".to_string(),
+            "2".to_string()
+        ]))
+    );
+    assert_eq!(
+        to_infix(&rpn("1 2 + x @ 3")),
+        Err(InfixError::LeftoverOperands(vec![
+            "1 + 2".to_string(),
+            "x".to_string(),
+            "3".to_string(),
+        ]))
+    );
+    assert_eq!(
+        InfixError::LeftoverOperands(vec!["1 + 2".to_string(), "x".to_string()]).to_string(),
+        "2 operands left on the stack: `1 + 2`, `x`"
+    );
+}
+
+#[test]
+fn test_round_trip_through_infix() {
+    for source in [
+        "10",
+        "4 8 + 7 5 - /",
+        "1 2 3 4 - - -",
+        "-7 2 / 3 * -1 -",
+        "2 3 4 * 5 6 / - +",
+        "2 3 2 ** ** -2 ** neg neg",
+        "5 neg -3 neg - 4 neg *",
+        "1 2 + 3 << 4 5 & | 6 ^ 7 < 0 ==",
+        "17 -5 % 3 -4 min max abs",
+        "4 x ! x @ 1 + y ! x @ y @ *",
+        "1 x ! 2 y !",
+    ] {
+        let inputs = rpn(source);
+        let infix = to_infix(&inputs).unwrap();
+        let parsed = crate::parse_infix(&infix).unwrap();
+
+        assert_eq!(parsed, inputs, "{}", infix);
+        assert_eq!(crate::evaluate(&parsed), crate::evaluate(&inputs));
+    }
+}
+
+#[test]
+fn test_round_trip_keeps_the_order_of_stores_and_loads() {
+    // The first `x @` reads 1, so this is 11; `x = 1; x = 2; x + 10` is 12.
+    let inputs = rpn("1 x ! x @ 2 x ! 10 +");
+    assert_eq!(crate::evaluate(&inputs), Ok(11));
+    assert_eq!(
+        to_infix(&inputs),
+        Err(InfixError::StoreWithPendingOperands { at: 4 })
+    );
+
+    let inputs = rpn("1 x ! x @ 10 + y ! 2 x ! y @");
+    let parsed = crate::parse_infix(&to_infix(&inputs).unwrap()).unwrap();
+    assert_eq!(parsed, inputs);
+    assert_eq!(crate::evaluate(&parsed), Ok(11));
+}
+
+#[test]
+fn test_to_rpn_statements() {
+    assert_eq!(to_rpn("x = 1 + 2; x * x"), Ok(rpn("1 2 + x ! x @ x @ *")));
+    assert_eq!(to_rpn("x = 1; y = x"), Ok(rpn("1 x ! x @ y !")));
+    assert_eq!(
+        to_rpn("(x = 1)"),
+        Err(ParseError::UnexpectedToken {
+            token: "=".to_string(),
+            span: 3..4,
+        })
+    );
+    assert_eq!(to_rpn("(1; 2)"), Err(ParseError::UnexpectedEnd { at: 2 }));
+}
+
+#[test]
+fn test_to_infix_statements() {
+    assert_eq!(
+        to_infix(&rpn("1 2 + x ! x @ x @ *")),
+        Ok("x = 1 + 2; x * x".to_string())
+    );
+    assert_eq!(
+        to_infix(&rpn("5 x ! x @ y !")),
+        Ok("x = 5; y = x".to_string())
+    );
+    assert_eq!(
+        to_infix(&rpn("x !")),
+        Err(InfixError::StackUnderflow { at: 0 })
+    );
+}
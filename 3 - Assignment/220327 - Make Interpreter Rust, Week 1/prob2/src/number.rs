@@ -0,0 +1,587 @@
+use crate::{ArithmeticMode, Division, EvalError, Numeric};
+use num_bigint::{BigInt, Sign};
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::ParseFloatError;
+use std::str::FromStr;
+
+/// Shifts, powers and products whose result would need more bits than
+/// this report `Overflow` instead of exhausting memory.
+const MAX_BITS: u64 = 1 << 20;
+
+/// A value of the numeric tower.
+///
+/// Integers grow into big integers instead of overflowing, exact division
+/// of integers produces rationals, and anything combined with a float is a
+/// float. Results are always kept in the narrowest exact form, so `4/2` is
+/// the integer 2.
+#[derive(Debug, Clone)]
+pub enum Number {
+    Integer(i64),
+    BigInteger(BigInt),
+    Rational(BigRational),
+    Float(f64),
+}
+
+/// Two operands promoted to a common representation.
+enum Operands {
+    Integer(i64, i64),
+    BigInteger(BigInt, BigInt),
+    Rational(BigRational, BigRational),
+    Float(f64, f64),
+}
+
+impl Number {
+    fn from_big(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(n) => Number::Integer(n),
+            None => Number::BigInteger(n),
+        }
+    }
+
+    fn from_ratio(r: BigRational) -> Self {
+        if r.is_integer() {
+            Number::from_big(r.to_integer())
+        } else {
+            Number::Rational(r)
+        }
+    }
+
+    fn integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Integer(n) => Some(BigInt::from(*n)),
+            Number::BigInteger(n) => Some(n.clone()),
+            Number::Rational(_) | Number::Float(_) => None,
+        }
+    }
+
+    fn ratio(&self) -> Option<BigRational> {
+        match self {
+            Number::Rational(r) => Some(r.clone()),
+            _ => self.integer().map(BigRational::from_integer),
+        }
+    }
+
+    fn float(&self) -> f64 {
+        match self {
+            Number::Integer(n) => *n as f64,
+            Number::BigInteger(n) => n.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Float(x) => *x,
+        }
+    }
+
+    /// Bits needed for the magnitude, or for the larger of numerator and
+    /// denominator. Floats never grow, so they need none.
+    fn bits(&self) -> u64 {
+        match self {
+            Number::Integer(n) => u64::from(u64::BITS - n.unsigned_abs().leading_zeros()),
+            Number::BigInteger(n) => n.bits(),
+            Number::Rational(r) => r.numer().bits().max(r.denom().bits()),
+            Number::Float(_) => 0,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(n) => *n == 0,
+            Number::BigInteger(n) => n.is_zero(),
+            Number::Rational(r) => r.is_zero(),
+            Number::Float(x) => *x == 0.0,
+        }
+    }
+
+    fn promote(&self, rhs: &Self) -> Operands {
+        use Number::*;
+
+        match (self, rhs) {
+            (Integer(a), Integer(b)) => Operands::Integer(*a, *b),
+            (Float(_), _) | (_, Float(_)) => Operands::Float(self.float(), rhs.float()),
+            (Rational(_), _) | (_, Rational(_)) => {
+                Operands::Rational(self.ratio().unwrap(), rhs.ratio().unwrap())
+            }
+            _ => Operands::BigInteger(self.integer().unwrap(), rhs.integer().unwrap()),
+        }
+    }
+
+    /// Combine two numbers, retrying in big integers when `integer`
+    /// overflows.
+    fn arithmetic(
+        &self,
+        rhs: &Self,
+        integer: fn(i64, i64) -> Option<i64>,
+        big: fn(BigInt, BigInt) -> BigInt,
+        rational: fn(BigRational, BigRational) -> BigRational,
+        float: fn(f64, f64) -> f64,
+    ) -> Self {
+        match self.promote(rhs) {
+            Operands::Integer(a, b) => match integer(a, b) {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(big(a.into(), b.into())),
+            },
+            Operands::BigInteger(a, b) => Number::from_big(big(a, b)),
+            Operands::Rational(a, b) => Number::from_ratio(rational(a, b)),
+            Operands::Float(a, b) => Number::Float(float(a, b)),
+        }
+    }
+
+    fn bitwise(
+        &self,
+        rhs: &Self,
+        f: fn(BigInt, BigInt) -> BigInt,
+    ) -> Result<Self, EvalError<Self>> {
+        match (self.integer(), rhs.integer()) {
+            (Some(a), Some(b)) => Ok(Number::from_big(f(a, b))),
+            _ => Err(EvalError::NotAnInteger),
+        }
+    }
+}
+
+impl Numeric for Number {
+    fn from_bool(value: bool) -> Self {
+        Number::Integer(value as i64)
+    }
+
+    fn negate(self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        Ok(match self {
+            Number::Integer(n) => match n.checked_neg() {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(-BigInt::from(n)),
+            },
+            Number::BigInteger(n) => Number::from_big(-n),
+            Number::Rational(r) => Number::Rational(-r),
+            Number::Float(x) => Number::Float(-x),
+        })
+    }
+
+    fn abs(self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        Ok(match self {
+            Number::Integer(n) => match n.checked_abs() {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(BigInt::from(n).abs()),
+            },
+            Number::BigInteger(n) => Number::BigInteger(n.abs()),
+            Number::Rational(r) => Number::Rational(r.abs()),
+            Number::Float(x) => Number::Float(x.abs()),
+        })
+    }
+
+    fn add(self, rhs: Self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        Ok(self.arithmetic(
+            &rhs,
+            i64::checked_add,
+            |a, b| a + b,
+            |a, b| a + b,
+            |a, b| a + b,
+        ))
+    }
+
+    fn subtract(self, rhs: Self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        Ok(self.arithmetic(
+            &rhs,
+            i64::checked_sub,
+            |a, b| a - b,
+            |a, b| a - b,
+            |a, b| a - b,
+        ))
+    }
+
+    fn multiply(self, rhs: Self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        if self.bits().saturating_add(rhs.bits()) > MAX_BITS {
+            return Err(EvalError::Overflow);
+        }
+
+        Ok(self.arithmetic(
+            &rhs,
+            i64::checked_mul,
+            |a, b| a * b,
+            |a, b| a * b,
+            |a, b| a * b,
+        ))
+    }
+
+    /// Truncating division rounds toward zero unless a float is involved.
+    /// Exact division keeps the fraction.
+    fn divide(
+        self,
+        rhs: Self,
+        division: Division,
+        _: ArithmeticMode,
+    ) -> Result<Self, EvalError<Self>> {
+        if rhs.is_zero() {
+            return Err(EvalError::DivisionByZero);
+        }
+
+        Ok(match (self.promote(&rhs), division) {
+            (Operands::Float(a, b), _) => Number::Float(a / b),
+            (Operands::Integer(a, b), Division::Truncating) => match a.checked_div(b) {
+                Some(n) => Number::Integer(n),
+                None => Number::from_big(BigInt::from(a) / b),
+            },
+            (Operands::BigInteger(a, b), Division::Truncating) => Number::from_big(a / b),
+            (Operands::Rational(a, b), Division::Truncating) => {
+                Number::from_big((a / b).to_integer())
+            }
+            (Operands::Integer(a, b), Division::Exact) => {
+                Number::from_ratio(BigRational::new(a.into(), b.into()))
+            }
+            (Operands::BigInteger(a, b), Division::Exact) => {
+                Number::from_ratio(BigRational::new(a, b))
+            }
+            (Operands::Rational(a, b), Division::Exact) => Number::from_ratio(a / b),
+        })
+    }
+
+    fn modulo(self, rhs: Self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        if rhs.is_zero() {
+            return Err(EvalError::DivisionByZero);
+        }
+
+        Ok(self.arithmetic(
+            &rhs,
+            i64::checked_rem,
+            |a, b| a % b,
+            |a, b| a % b,
+            |a, b| a % b,
+        ))
+    }
+
+    /// A negative integer exponent gives the exact reciprocal; a
+    /// fractional exponent falls back to floats.
+    fn power(self, rhs: Self, _: ArithmeticMode) -> Result<Self, EvalError<Self>> {
+        let (base, exponent) = match (self.ratio(), rhs.integer()) {
+            (Some(base), Some(exponent)) => (base, exponent),
+            _ => return Ok(Number::Float(self.float().powf(rhs.float()))),
+        };
+
+        // 0, 1 and -1 stay small whatever the exponent.
+        if base.is_zero() {
+            return match exponent.sign() {
+                Sign::Minus => Err(EvalError::DivisionByZero),
+                Sign::NoSign => Ok(Number::Integer(1)),
+                Sign::Plus => Ok(Number::Integer(0)),
+            };
+        }
+        if base.abs().is_one() {
+            let sign = if base.is_negative() && exponent.bit(0) {
+                -1
+            } else {
+                1
+            };
+            return Ok(Number::Integer(sign));
+        }
+
+        let bits = self.bits();
+        let exponent = exponent
+            .to_i32()
+            .filter(|exponent| bits.saturating_mul(exponent.unsigned_abs().into()) <= MAX_BITS)
+            .ok_or(EvalError::Overflow)?;
+
+        Ok(Number::from_ratio(base.pow(exponent)))
+    }
+
+    fn and(self, rhs: Self) -> Result<Self, EvalError<Self>> {
+        self.bitwise(&rhs, |a, b| a & b)
+    }
+
+    fn or(self, rhs: Self) -> Result<Self, EvalError<Self>> {
+        self.bitwise(&rhs, |a, b| a | b)
+    }
+
+    fn xor(self, rhs: Self) -> Result<Self, EvalError<Self>> {
+        self.bitwise(&rhs, |a, b| a ^ b)
+    }
+
+    fn shl(self, rhs: Self) -> Result<Self, EvalError<Self>> {
+        let (value, shift) = self
+            .integer()
+            .zip(rhs.integer())
+            .ok_or(EvalError::NotAnInteger)?;
+        let shift = shift
+            .to_u64()
+            .filter(|shift| value.bits().saturating_add(*shift) <= MAX_BITS)
+            .ok_or(EvalError::Overflow)?;
+
+        Ok(Number::from_big(value << shift))
+    }
+
+    fn shr(self, rhs: Self) -> Result<Self, EvalError<Self>> {
+        let (value, shift) = self
+            .integer()
+            .zip(rhs.integer())
+            .ok_or(EvalError::NotAnInteger)?;
+        let shift = shift.to_u64().ok_or(EvalError::Overflow)?;
+
+        // Shifting past the last bit leaves 0 or -1, like `>>` on `i32`.
+        let shift = shift.min(value.bits());
+        Ok(Number::from_big(value >> shift))
+    }
+}
+
+/// Numbers compare by value, whatever their representation.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.promote(other) {
+            Operands::Integer(a, b) => a.partial_cmp(&b),
+            Operands::BigInteger(a, b) => a.partial_cmp(&b),
+            Operands::Rational(a, b) => a.partial_cmp(&b),
+            Operands::Float(a, b) => a.partial_cmp(&b),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Integer(n) => write!(f, "{}", n),
+            Number::BigInteger(n) => write!(f, "{}", n),
+            Number::Rational(r) => write!(f, "{}", r),
+            // `{:?}` keeps the decimal point, so `2.0` reads back as a float.
+            Number::Float(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+/// Reads integers of any size, fractions such as `2/3`, and decimals.
+impl FromStr for Number {
+    type Err = ParseFloatError;
+
+    fn from_str(literal: &str) -> Result<Self, Self::Err> {
+        if let Ok(n) = literal.parse::<i64>() {
+            return Ok(Number::Integer(n));
+        }
+        if let Ok(n) = literal.parse::<BigInt>() {
+            return Ok(Number::from_big(n));
+        }
+        if literal.contains('/') {
+            if let Ok(r) = literal.parse::<BigRational>() {
+                return Ok(Number::from_ratio(r));
+            }
+        }
+
+        literal.parse().map(Number::Float)
+    }
+}
+
+impl From<i32> for Number {
+    fn from(n: i32) -> Self {
+        Number::Integer(n.into())
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        Number::Integer(n)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(x: f64) -> Self {
+        Number::Float(x)
+    }
+}
+
+impl From<BigInt> for Number {
+    fn from(n: BigInt) -> Self {
+        Number::from_big(n)
+    }
+}
+
+impl From<BigRational> for Number {
+    fn from(r: BigRational) -> Self {
+        Number::from_ratio(r)
+    }
+}
+
+#[cfg(test)]
+fn evaluate(source: &str, division: Division) -> Result<String, EvalError<Number>> {
+    let inputs = crate::tokenize_as::<Number>(source).unwrap();
+    crate::evaluate_with(
+        &inputs,
+        crate::Options {
+            division,
+            ..Default::default()
+        },
+    )
+    .map(|n| n.to_string())
+}
+
+#[test]
+fn test_division_modes() {
+    assert_eq!(
+        evaluate("57 19 /", Division::Truncating),
+        Ok("3".to_string())
+    );
+    assert_eq!(evaluate("57 19 /", Division::Exact), Ok("3".to_string()));
+    assert_eq!(
+        evaluate("-7 2 /", Division::Truncating),
+        Ok("-3".to_string())
+    );
+    assert_eq!(evaluate("-7 2 /", Division::Exact), Ok("-7/2".to_string()));
+    assert_eq!(evaluate("7 2 / 2 *", Division::Exact), Ok("7".to_string()));
+    assert_eq!(
+        evaluate("1 0 /", Division::Exact),
+        Err(EvalError::DivisionByZero)
+    );
+}
+
+#[test]
+fn test_exact_rational_arithmetic() {
+    assert_eq!(
+        evaluate("1 3 / 1 6 / +", Division::Exact),
+        Ok("1/2".to_string())
+    );
+    assert_eq!(
+        evaluate("2/3 3/4 *", Division::Exact),
+        Ok("1/2".to_string())
+    );
+    assert_eq!(
+        evaluate("7/2 3 /", Division::Truncating),
+        Ok("1".to_string())
+    );
+    assert_eq!(evaluate("7/2 1 %", Division::Exact), Ok("1/2".to_string()));
+    assert_eq!(
+        evaluate("1 3 / 0.3 >", Division::Exact),
+        Ok("1".to_string())
+    );
+}
+
+#[test]
+fn test_integers_grow_instead_of_overflowing() {
+    assert_eq!(
+        evaluate("9223372036854775807 1 +", Division::Truncating),
+        Ok("9223372036854775808".to_string())
+    );
+    assert_eq!(
+        evaluate("-9223372036854775808 -1 /", Division::Truncating),
+        Ok("9223372036854775808".to_string())
+    );
+    assert_eq!(
+        evaluate("2 100 **", Division::Truncating),
+        Ok("1267650600228229401496703205376".to_string())
+    );
+    assert_eq!(
+        evaluate(
+            "99999999999999999999 99999999999999999998 - 1 ==",
+            Division::Truncating
+        ),
+        Ok("1".to_string())
+    );
+    assert_eq!(
+        evaluate("2 10000000 **", Division::Truncating),
+        Err(EvalError::Overflow)
+    );
+}
+
+#[test]
+fn test_floats_are_contagious() {
+    assert_eq!(
+        evaluate("1.5 2 *", Division::Truncating),
+        Ok("3.0".to_string())
+    );
+    assert_eq!(
+        evaluate("7 2.0 /", Division::Truncating),
+        Ok("3.5".to_string())
+    );
+    assert_eq!(evaluate("4 0.5 **", Division::Exact), Ok("2.0".to_string()));
+    assert_eq!(
+        evaluate("1.5 1 &", Division::Exact),
+        Err(EvalError::NotAnInteger)
+    );
+}
+
+#[test]
+fn test_powers_and_shifts() {
+    assert_eq!(
+        evaluate("2 -2 **", Division::Truncating),
+        Ok("1/4".to_string())
+    );
+    assert_eq!(
+        evaluate("2/3 2 **", Division::Truncating),
+        Ok("4/9".to_string())
+    );
+    assert_eq!(
+        evaluate("0 -1 **", Division::Truncating),
+        Err(EvalError::DivisionByZero)
+    );
+    assert_eq!(
+        evaluate("2 99999999999999999999 **", Division::Truncating),
+        Err(EvalError::Overflow)
+    );
+    assert_eq!(
+        evaluate("1/2 3000000000 **", Division::Truncating),
+        Err(EvalError::Overflow)
+    );
+    assert_eq!(
+        evaluate("1 70 << 68 >>", Division::Truncating),
+        Ok("4".to_string())
+    );
+    assert_eq!(
+        evaluate("-5 100 >>", Division::Truncating),
+        Ok("-1".to_string())
+    );
+    assert_eq!(
+        evaluate("1 -1 <<", Division::Truncating),
+        Err(EvalError::Overflow)
+    );
+}
+
+#[test]
+fn test_powers_of_zero_and_one_never_overflow() {
+    for (source, result) in [
+        ("1 99999999999999999999 **", Ok("1")),
+        ("1 -99999999999999999999 **", Ok("1")),
+        ("-1 99999999999999999999 **", Ok("-1")),
+        ("-1 -3000000000 **", Ok("1")),
+        ("0 99999999999999999999 **", Ok("0")),
+        ("0 0 **", Ok("1")),
+        ("0 -99999999999999999999 **", Err(EvalError::DivisionByZero)),
+    ] {
+        assert_eq!(
+            evaluate(source, Division::Truncating),
+            result.map(str::to_string),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_products_are_bounded_too() {
+    assert!(evaluate("3 400000 ** dup -", Division::Truncating).is_ok());
+    assert_eq!(
+        evaluate("3 400000 ** dup *", Division::Truncating),
+        Err(EvalError::Overflow)
+    );
+    assert_eq!(
+        evaluate("2/3 500000 ** dup *", Division::Exact),
+        Err(EvalError::Overflow)
+    );
+}
+
+#[test]
+fn test_number_literals() {
+    use crate::{tokenize_as, CalculatorInput, LexError};
+
+    assert_eq!(
+        tokenize_as::<Number>("6/4"),
+        Ok(vec![CalculatorInput::Value(Number::Rational(
+            BigRational::new(3.into(), 2.into())
+        ))])
+    );
+    assert_eq!(
+        tokenize_as::<Number>("1/0"),
+        Err(LexError::InvalidNumber {
+            literal: "1/0".to_string(),
+            span: 0..3,
+        })
+    );
+}
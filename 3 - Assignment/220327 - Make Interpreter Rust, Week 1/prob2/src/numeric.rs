@@ -0,0 +1,205 @@
+use crate::EvalError;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// How `Divide` treats a quotient that is not a whole number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Division {
+    /// Round toward zero, like Rust's integer division.
+    #[default]
+    Truncating,
+    /// Keep the exact quotient, if the value type can hold it.
+    Exact,
+}
+
+/// What fixed-width arithmetic does when a result does not fit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ArithmeticMode {
+    /// Fail with `EvalError::Overflow`.
+    #[default]
+    Checked,
+    /// Wrap around at the boundary of the type, like two's complement
+    /// hardware does.
+    Wrapping,
+    /// Stop at the largest or smallest value of the type.
+    Saturating,
+}
+
+/// Settings for `evaluate_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Options {
+    pub division: Division,
+    pub arithmetic: ArithmeticMode,
+    /// Most inputs one evaluation may run, so that a loop that never ends
+    /// fails with `StepLimitExceeded` instead of hanging.
+    pub step_limit: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            division: Division::default(),
+            arithmetic: ArithmeticMode::default(),
+            step_limit: 1_000_000,
+        }
+    }
+}
+
+/// The arithmetic `evaluate` needs from the values on its stack.
+///
+/// `i32` handles overflow as the `ArithmeticMode` asks, while `Number`
+/// promotes to a wider representation instead and ignores the mode.
+pub trait Numeric: Clone + Debug + PartialOrd + FromStr {
+    /// The value pushed by comparisons: 1 for true, 0 for false.
+    fn from_bool(value: bool) -> Self;
+
+    fn negate(self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+    fn abs(self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+
+    fn add(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+    fn subtract(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+    fn multiply(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+    fn divide(
+        self,
+        rhs: Self,
+        division: Division,
+        mode: ArithmeticMode,
+    ) -> Result<Self, EvalError<Self>>;
+    fn modulo(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+    fn power(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError<Self>>;
+
+    fn and(self, rhs: Self) -> Result<Self, EvalError<Self>>;
+    fn or(self, rhs: Self) -> Result<Self, EvalError<Self>>;
+    fn xor(self, rhs: Self) -> Result<Self, EvalError<Self>>;
+    fn shl(self, rhs: Self) -> Result<Self, EvalError<Self>>;
+    fn shr(self, rhs: Self) -> Result<Self, EvalError<Self>>;
+}
+
+/// Pick the result `mode` asks for out of the three ways to compute it.
+fn in_mode(
+    mode: ArithmeticMode,
+    checked: Option<i32>,
+    wrapping: i32,
+    saturating: i32,
+) -> Result<i32, EvalError> {
+    match mode {
+        ArithmeticMode::Checked => checked.ok_or(EvalError::Overflow),
+        ArithmeticMode::Wrapping => Ok(wrapping),
+        ArithmeticMode::Saturating => Ok(saturating),
+    }
+}
+
+/// Fixed-width integers. There is nowhere to keep a fraction, so both
+/// division modes truncate. Shifts by a negative amount or by 32 or more
+/// fail in every `ArithmeticMode`, since they are not arithmetic overflow.
+impl Numeric for i32 {
+    fn from_bool(value: bool) -> Self {
+        value as i32
+    }
+
+    fn negate(self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        in_mode(
+            mode,
+            self.checked_neg(),
+            self.wrapping_neg(),
+            self.saturating_neg(),
+        )
+    }
+
+    fn abs(self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        in_mode(
+            mode,
+            self.checked_abs(),
+            self.wrapping_abs(),
+            self.saturating_abs(),
+        )
+    }
+
+    fn add(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        in_mode(
+            mode,
+            self.checked_add(rhs),
+            self.wrapping_add(rhs),
+            self.saturating_add(rhs),
+        )
+    }
+
+    fn subtract(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        in_mode(
+            mode,
+            self.checked_sub(rhs),
+            self.wrapping_sub(rhs),
+            self.saturating_sub(rhs),
+        )
+    }
+
+    fn multiply(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        in_mode(
+            mode,
+            self.checked_mul(rhs),
+            self.wrapping_mul(rhs),
+            self.saturating_mul(rhs),
+        )
+    }
+
+    /// Dividing by zero fails in every mode.
+    fn divide(self, rhs: Self, _: Division, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        match rhs {
+            0 => Err(EvalError::DivisionByZero),
+            _ => in_mode(
+                mode,
+                self.checked_div(rhs),
+                self.wrapping_div(rhs),
+                self.saturating_div(rhs),
+            ),
+        }
+    }
+
+    /// The only overflow, `i32::MIN % -1`, is 0 when it does not fail.
+    fn modulo(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        match rhs {
+            0 => Err(EvalError::DivisionByZero),
+            _ => in_mode(
+                mode,
+                self.checked_rem(rhs),
+                self.wrapping_rem(rhs),
+                self.wrapping_rem(rhs),
+            ),
+        }
+    }
+
+    fn power(self, rhs: Self, mode: ArithmeticMode) -> Result<Self, EvalError> {
+        let exponent = u32::try_from(rhs).map_err(|_| EvalError::NegativeExponent)?;
+        in_mode(
+            mode,
+            self.checked_pow(exponent),
+            self.wrapping_pow(exponent),
+            self.saturating_pow(exponent),
+        )
+    }
+
+    fn and(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self & rhs)
+    }
+
+    fn or(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self | rhs)
+    }
+
+    fn xor(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self ^ rhs)
+    }
+
+    fn shl(self, rhs: Self) -> Result<Self, EvalError> {
+        let shift = u32::try_from(rhs).map_err(|_| EvalError::Overflow)?;
+        self.checked_shl(shift).ok_or(EvalError::Overflow)
+    }
+
+    fn shr(self, rhs: Self) -> Result<Self, EvalError> {
+        let shift = u32::try_from(rhs).map_err(|_| EvalError::Overflow)?;
+        self.checked_shr(shift).ok_or(EvalError::Overflow)
+    }
+}
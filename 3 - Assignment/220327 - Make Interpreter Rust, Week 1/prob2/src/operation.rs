@@ -0,0 +1,216 @@
+use crate::control::Control;
+use crate::{CalculatorInput, EvalError, Numeric, Options};
+
+/// What an input does to the stack. Every input is looked up here, so
+/// `handle_input` only needs to know how many operands to pop.
+pub(crate) enum Operation<'a, N> {
+    Push(N),
+    Unary(fn(N, &Options) -> Result<N, EvalError<N>>),
+    Binary(fn(N, N, &Options) -> Result<N, EvalError<N>>),
+    /// Pop `pops` values and push them back in the order given by
+    /// `pushes`, which holds indices into the popped values.
+    Shuffle {
+        pops: usize,
+        pushes: &'static [usize],
+    },
+    Clear,
+    Store(&'a str),
+    Load(&'a str),
+    /// Handled by `control::step`, since it moves the program counter.
+    Control(Control),
+}
+
+impl<N> Operation<'_, N> {
+    /// Number of operands popped from the stack.
+    pub(crate) fn arity(&self) -> usize {
+        match self {
+            Operation::Push(_) | Operation::Clear | Operation::Load(_) => 0,
+            Operation::Unary(_) | Operation::Store(_) => 1,
+            Operation::Control(Control::If | Control::Until) => 1,
+            Operation::Control(_) => 0,
+            Operation::Binary(_) => 2,
+            Operation::Shuffle { pops, .. } => *pops,
+        }
+    }
+
+    /// Number of values pushed back after the operands are popped.
+    pub(crate) fn pushes(&self) -> usize {
+        match self {
+            Operation::Clear | Operation::Store(_) | Operation::Control(_) => 0,
+            Operation::Push(_) | Operation::Load(_) => 1,
+            Operation::Unary(_) | Operation::Binary(_) => 1,
+            Operation::Shuffle { pushes, .. } => pushes.len(),
+        }
+    }
+}
+
+impl<N: Numeric> CalculatorInput<N> {
+    pub(crate) fn operation(&self) -> Operation<'_, N> {
+        use CalculatorInput::*;
+
+        match self {
+            Value(n) => Operation::Push(n.clone()),
+
+            Negate => Operation::Unary(|a, options| a.negate(options.arithmetic)),
+            Abs => Operation::Unary(|a, options| a.abs(options.arithmetic)),
+
+            Add => Operation::Binary(|a, b, options| a.add(b, options.arithmetic)),
+            Subtract => Operation::Binary(|a, b, options| a.subtract(b, options.arithmetic)),
+            Multiply => Operation::Binary(|a, b, options| a.multiply(b, options.arithmetic)),
+            Divide => {
+                Operation::Binary(|a, b, options| a.divide(b, options.division, options.arithmetic))
+            }
+            Modulo => Operation::Binary(|a, b, options| a.modulo(b, options.arithmetic)),
+            Power => Operation::Binary(|a, b, options| a.power(b, options.arithmetic)),
+
+            And => Operation::Binary(|a, b, _| a.and(b)),
+            Or => Operation::Binary(|a, b, _| a.or(b)),
+            Xor => Operation::Binary(|a, b, _| a.xor(b)),
+            Shl => Operation::Binary(|a, b, _| a.shl(b)),
+            Shr => Operation::Binary(|a, b, _| a.shr(b)),
+
+            Equal => Operation::Binary(|a, b, _| Ok(N::from_bool(a == b))),
+            NotEqual => Operation::Binary(|a, b, _| Ok(N::from_bool(a != b))),
+            Less => Operation::Binary(|a, b, _| Ok(N::from_bool(a < b))),
+            LessEqual => Operation::Binary(|a, b, _| Ok(N::from_bool(a <= b))),
+            Greater => Operation::Binary(|a, b, _| Ok(N::from_bool(a > b))),
+            GreaterEqual => Operation::Binary(|a, b, _| Ok(N::from_bool(a >= b))),
+
+            Min => Operation::Binary(|a, b, _| Ok(if b < a { b } else { a })),
+            Max => Operation::Binary(|a, b, _| Ok(if b > a { b } else { a })),
+
+            Dup => Operation::Shuffle {
+                pops: 1,
+                pushes: &[0, 0],
+            },
+            Drop => Operation::Shuffle {
+                pops: 1,
+                pushes: &[],
+            },
+            Swap => Operation::Shuffle {
+                pops: 2,
+                pushes: &[1, 0],
+            },
+            Over => Operation::Shuffle {
+                pops: 2,
+                pushes: &[0, 1, 0],
+            },
+            Rot => Operation::Shuffle {
+                pops: 3,
+                pushes: &[1, 2, 0],
+            },
+            Clear => Operation::Clear,
+
+            Store(name) => Operation::Store(name),
+            Load(name) => Operation::Load(name),
+
+            If | Else | Then | Begin | Until => Operation::Control(self.control().unwrap()),
+        }
+    }
+
+    /// Number of operands this input pops from the stack.
+    pub(crate) fn arity(&self) -> usize {
+        self.operation().arity()
+    }
+}
+
+#[cfg(test)]
+fn evaluate(source: &str) -> Result<i32, EvalError> {
+    crate::evaluate(&crate::tokenize(source).unwrap())
+}
+
+#[test]
+fn test_modulo() {
+    assert_eq!(evaluate("17 5 %"), Ok(2));
+    assert_eq!(evaluate("-17 5 %"), Ok(-2));
+    assert_eq!(evaluate("17 0 %"), Err(EvalError::DivisionByZero));
+    assert_eq!(evaluate("-2147483648 -1 %"), Err(EvalError::Overflow));
+}
+
+#[test]
+fn test_power() {
+    assert_eq!(evaluate("2 10 **"), Ok(1024));
+    assert_eq!(evaluate("7 0 **"), Ok(1));
+    assert_eq!(evaluate("2 -1 **"), Err(EvalError::NegativeExponent));
+    assert_eq!(evaluate("2 31 **"), Err(EvalError::Overflow));
+}
+
+#[test]
+fn test_unary_operators() {
+    assert_eq!(evaluate("5 neg"), Ok(-5));
+    assert_eq!(evaluate("-5 abs"), Ok(5));
+    assert_eq!(evaluate("-2147483648 neg"), Err(EvalError::Overflow));
+    assert_eq!(evaluate("-2147483648 abs"), Err(EvalError::Overflow));
+    assert_eq!(evaluate("neg"), Err(EvalError::StackUnderflow { at: 0 }));
+}
+
+#[test]
+fn test_bitwise_operators() {
+    assert_eq!(evaluate("12 10 &"), Ok(8));
+    assert_eq!(evaluate("12 10 |"), Ok(14));
+    assert_eq!(evaluate("12 10 ^"), Ok(6));
+    assert_eq!(evaluate("1 4 <<"), Ok(16));
+    assert_eq!(evaluate("-16 2 >>"), Ok(-4));
+    assert_eq!(evaluate("1 32 <<"), Err(EvalError::Overflow));
+    assert_eq!(evaluate("1 -1 >>"), Err(EvalError::Overflow));
+}
+
+#[test]
+fn test_comparison_operators_push_zero_or_one() {
+    assert_eq!(evaluate("3 3 =="), Ok(1));
+    assert_eq!(evaluate("3 4 !="), Ok(1));
+    assert_eq!(evaluate("3 4 <"), Ok(1));
+    assert_eq!(evaluate("4 4 <="), Ok(1));
+    assert_eq!(evaluate("3 4 >"), Ok(0));
+    assert_eq!(evaluate("3 4 >="), Ok(0));
+}
+
+#[test]
+fn test_min_and_max() {
+    assert_eq!(evaluate("3 -4 min"), Ok(-4));
+    assert_eq!(evaluate("3 -4 max"), Ok(3));
+}
+
+#[test]
+fn test_dup() {
+    assert_eq!(evaluate("3 dup *"), Ok(9));
+    assert_eq!(evaluate("dup"), Err(EvalError::StackUnderflow { at: 0 }));
+}
+
+#[test]
+fn test_drop() {
+    assert_eq!(evaluate("1 2 drop"), Ok(1));
+    assert_eq!(evaluate("1 drop"), Err(EvalError::EmptyStack));
+}
+
+#[test]
+fn test_swap() {
+    assert_eq!(evaluate("1 2 swap -"), Ok(1));
+    assert_eq!(evaluate("1 swap"), Err(EvalError::StackUnderflow { at: 1 }));
+}
+
+#[test]
+fn test_over() {
+    assert_eq!(
+        evaluate("1 2 over"),
+        Err(EvalError::LeftoverOperands(vec![1, 2, 1]))
+    );
+}
+
+#[test]
+fn test_rot() {
+    assert_eq!(
+        evaluate("1 2 3 rot"),
+        Err(EvalError::LeftoverOperands(vec![2, 3, 1]))
+    );
+    assert_eq!(
+        evaluate("1 2 rot"),
+        Err(EvalError::StackUnderflow { at: 2 })
+    );
+}
+
+#[test]
+fn test_clear() {
+    assert_eq!(evaluate("1 2 3 clear 4"), Ok(4));
+    assert_eq!(evaluate("1 2 clear"), Err(EvalError::EmptyStack));
+}
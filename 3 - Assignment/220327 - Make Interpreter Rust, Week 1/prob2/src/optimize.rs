@@ -0,0 +1,226 @@
+use crate::operation::Operation;
+use crate::{verify, CalculatorInput, Options};
+
+/// Fold constant sub-expressions and drop inputs that cannot change the
+/// result, so that `2 3 + x @ *` becomes `5 x @ *` and `x @ 0 +` becomes
+/// `x @`.
+///
+/// `evaluate` gives the same result, or the same error, on the optimized
+/// program as on the original. Operations that fail on constants, such as
+/// `1 0 /`, are kept so they still fail when the program runs, and
+/// programs rejected by `verify` are returned unchanged so that their
+/// errors keep pointing at the same inputs. The one difference is that the
+/// optimized program takes fewer steps, so it may finish within
+/// `Options::step_limit` where the original would not.
+pub fn optimize(inputs: &[CalculatorInput]) -> Vec<CalculatorInput> {
+    if verify(inputs).is_err() {
+        return inputs.to_vec();
+    }
+
+    let mut output: Vec<CalculatorInput> = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        if !fold(&mut output, input) && !cancel(&mut output, input) {
+            output.push(input.clone());
+        }
+    }
+
+    output
+}
+
+/// Replace `input` and the constants it works on with its result. Returns
+/// false if the operands are not all constants or the operation fails.
+fn fold(output: &mut Vec<CalculatorInput>, input: &CalculatorInput) -> bool {
+    let operation = input.operation();
+    let arity = operation.arity();
+    if arity == 0 || output.len() < arity {
+        return false;
+    }
+
+    let start = output.len() - arity;
+    let operands: Vec<i32> = match output[start..]
+        .iter()
+        .map(|operand| match operand {
+            CalculatorInput::Value(n) => Some(*n),
+            _ => None,
+        })
+        .collect()
+    {
+        Some(operands) => operands,
+        None => return false,
+    };
+
+    let results = match operation {
+        Operation::Unary(operator) => match operator(operands[0], &Options::default()) {
+            Ok(result) => vec![result],
+            Err(_) => return false,
+        },
+        Operation::Binary(operator) => {
+            match operator(operands[0], operands[1], &Options::default()) {
+                Ok(result) => vec![result],
+                Err(_) => return false,
+            }
+        }
+        Operation::Shuffle { pushes, .. } => pushes.iter().map(|&index| operands[index]).collect(),
+        _ => return false,
+    };
+
+    output.truncate(start);
+    output.extend(results.into_iter().map(CalculatorInput::Value));
+    true
+}
+
+/// Drop `input` together with the input before it when the pair leaves the
+/// stack as it was, like `0 +`, `1 *` or `swap swap`.
+fn cancel(output: &mut Vec<CalculatorInput>, input: &CalculatorInput) -> bool {
+    use CalculatorInput::*;
+
+    let cancels = match (output.last(), input) {
+        (Some(Value(0)), Add | Subtract | Or | Xor | Shl | Shr) => true,
+        (Some(Value(1)), Multiply | Divide | Power) => true,
+        (Some(Value(-1)), And) => true,
+        (Some(Dup), Drop) | (Some(Swap), Swap) => true,
+        // If the first `abs` succeeds the second cannot fail, so only the
+        // second one is dropped.
+        (Some(Abs), Abs) => return true,
+        _ => false,
+    };
+
+    if cancels {
+        output.pop();
+    }
+    cancels
+}
+
+#[cfg(test)]
+fn optimize_source(source: &str) -> Vec<CalculatorInput> {
+    optimize(&crate::tokenize(source).unwrap())
+}
+
+#[cfg(test)]
+fn rpn(source: &str) -> Vec<CalculatorInput> {
+    crate::tokenize(source).unwrap()
+}
+
+#[test]
+fn test_optimize_folds_constants() {
+    assert_eq!(optimize_source("2 3 + x @ *"), rpn("5 x @ *"));
+    assert_eq!(optimize_source("4 8 + 7 5 - /"), rpn("6"));
+    assert_eq!(optimize_source("3 dup * x @ swap -"), rpn("9 x @ swap -"));
+    assert_eq!(optimize_source("x @ 1 2 3 rot drop * +"), rpn("x @ 6 +"));
+}
+
+#[test]
+fn test_optimize_removes_identities() {
+    assert_eq!(optimize_source("x @ 0 + 1 * 0 -"), rpn("x @"));
+    assert_eq!(optimize_source("x @ 1 1 - +"), rpn("x @"));
+    assert_eq!(
+        optimize_source("x @ y @ swap swap dup drop -"),
+        rpn("x @ y @ -")
+    );
+    assert_eq!(optimize_source("x @ abs abs abs"), rpn("x @ abs"));
+}
+
+#[test]
+fn test_optimize_keeps_failing_operations() {
+    assert_eq!(optimize_source("1 0 / x @ +"), rpn("1 0 / x @ +"));
+    assert_eq!(optimize_source("2147483647 1 +"), rpn("2147483647 1 +"));
+    assert_eq!(optimize_source("x @ neg neg"), rpn("x @ neg neg"));
+}
+
+#[test]
+fn test_optimize_leaves_malformed_programs_alone() {
+    assert_eq!(optimize_source("0 +"), rpn("0 +"));
+    assert_eq!(optimize_source("1 2 + 3 4"), rpn("1 2 + 3 4"));
+    assert_eq!(optimize_source("1 if 2 3 + then"), rpn("1 if 2 3 + then"));
+}
+
+#[test]
+fn test_optimize_inside_control_flow() {
+    assert_eq!(
+        optimize_source("x @ if 2 3 + else 0 then 1 *"),
+        rpn("x @ if 5 else 0 then")
+    );
+    assert_eq!(
+        optimize_source("0 begin 1 2 + + dup 10 > until"),
+        rpn("0 begin 3 + dup 10 > until")
+    );
+    // `5` and `1` are constants, but the `begin` between them is not.
+    assert_eq!(
+        optimize_source("5 begin 1 - dup 0 == until"),
+        rpn("5 begin 1 - dup 0 == until")
+    );
+}
+
+#[cfg(test)]
+mod properties {
+    use super::optimize;
+    use crate::{evaluate, verify, CalculatorInput};
+    use proptest::prelude::*;
+
+    fn input() -> impl Strategy<Value = CalculatorInput> {
+        use CalculatorInput::*;
+
+        prop_oneof![
+            4 => prop_oneof![Just(0), Just(1), Just(-1), Just(i32::MIN), Just(i32::MAX), -40..40]
+                .prop_map(Value),
+            1 => prop::sample::select(vec!["x", "y"]).prop_map(|name| Store(name.to_string())),
+            1 => prop::sample::select(vec!["x", "y"]).prop_map(|name| Load(name.to_string())),
+            4 => prop::sample::select(vec![
+                Add, Subtract, Multiply, Divide, Modulo, Power, Negate, Abs, And, Or, Xor, Shl,
+                Shr, Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual, Min, Max, Dup, Drop,
+                Swap, Over, Rot, Clear,
+            ]),
+        ]
+    }
+
+    /// Most random programs fail `verify` and are left alone, so also try
+    /// them with the inputs that would underflow skipped and the leftover
+    /// operands added together.
+    fn well_formed(inputs: Vec<CalculatorInput>) -> Vec<CalculatorInput> {
+        let mut output = vec![];
+        let mut depth = 0;
+        for input in inputs {
+            let operation = input.operation();
+            if depth < operation.arity() {
+                continue;
+            }
+            depth = match input {
+                CalculatorInput::Clear => 0,
+                _ => depth - operation.arity() + operation.pushes(),
+            };
+            output.push(input);
+        }
+
+        if depth == 0 {
+            output.push(CalculatorInput::Value(1));
+            depth = 1;
+        }
+        output.extend((1..depth).map(|_| CalculatorInput::Add));
+        output
+    }
+
+    proptest! {
+        #[test]
+        fn optimized_programs_evaluate_identically(
+            inputs in prop::collection::vec(input(), 0..24),
+        ) {
+            prop_assert_eq!(evaluate(&optimize(&inputs)), evaluate(&inputs));
+        }
+
+        #[test]
+        fn optimized_well_formed_programs_evaluate_identically(
+            inputs in prop::collection::vec(input(), 0..24).prop_map(well_formed),
+        ) {
+            prop_assert!(verify(&inputs).is_ok());
+            prop_assert_eq!(evaluate(&optimize(&inputs)), evaluate(&inputs));
+        }
+
+        #[test]
+        fn optimizing_twice_changes_nothing(
+            inputs in prop::collection::vec(input(), 0..24).prop_map(well_formed),
+        ) {
+            let optimized = optimize(&inputs);
+            prop_assert_eq!(optimize(&optimized), optimized);
+        }
+    }
+}
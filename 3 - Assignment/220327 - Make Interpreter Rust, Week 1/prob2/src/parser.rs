@@ -0,0 +1,579 @@
+use crate::expression::{Expression, Statement};
+use crate::{CalculatorInput, LexError, Span};
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    Lex(LexError),
+    UnexpectedToken {
+        token: String,
+        span: Span,
+    },
+    UnexpectedEnd {
+        at: usize,
+    },
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        ParseError::Lex(error)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Lex(error) => error.fmt(f),
+            ParseError::UnexpectedToken { token, span } => {
+                write!(f, "unexpected `{}` at {}", token, span.start)
+            }
+            ParseError::UnexpectedEnd { .. } => write!(f, "unexpected end of input"),
+            ParseError::WrongArgumentCount {
+                function,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "`{}` takes {} arguments but {} were given",
+                function, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Associativity {
+    Left,
+    Right,
+}
+
+pub(crate) struct InfixOperator {
+    pub(crate) symbol: &'static str,
+    pub(crate) input: CalculatorInput,
+    pub(crate) precedence: u8,
+    pub(crate) associativity: Associativity,
+}
+
+const fn left(symbol: &'static str, input: CalculatorInput, precedence: u8) -> InfixOperator {
+    InfixOperator {
+        symbol,
+        input,
+        precedence,
+        associativity: Associativity::Left,
+    }
+}
+
+const fn right(symbol: &'static str, input: CalculatorInput, precedence: u8) -> InfixOperator {
+    InfixOperator {
+        symbol,
+        input,
+        precedence,
+        associativity: Associativity::Right,
+    }
+}
+
+/// Binary operators, loosest first. Precedence follows Rust, with `**`
+/// added above unary minus so that `-2 ** 2` is `-(2 ** 2)`.
+pub(crate) const INFIX_OPERATORS: &[InfixOperator] = &[
+    left("==", CalculatorInput::Equal, 1),
+    left("!=", CalculatorInput::NotEqual, 1),
+    left("<", CalculatorInput::Less, 1),
+    left("<=", CalculatorInput::LessEqual, 1),
+    left(">", CalculatorInput::Greater, 1),
+    left(">=", CalculatorInput::GreaterEqual, 1),
+    left("|", CalculatorInput::Or, 2),
+    left("^", CalculatorInput::Xor, 3),
+    left("&", CalculatorInput::And, 4),
+    left("<<", CalculatorInput::Shl, 5),
+    left(">>", CalculatorInput::Shr, 5),
+    left("+", CalculatorInput::Add, 6),
+    left("-", CalculatorInput::Subtract, 6),
+    left("*", CalculatorInput::Multiply, 7),
+    left("/", CalculatorInput::Divide, 7),
+    left("%", CalculatorInput::Modulo, 7),
+    right("**", CalculatorInput::Power, 9),
+];
+
+/// Unary minus binds tighter than every binary operator except `**`.
+pub(crate) const PREFIX_PRECEDENCE: u8 = 8;
+
+/// Inputs written as function calls in infix text, e.g. `min(a, b)`.
+pub(crate) const FUNCTIONS: &[(&str, CalculatorInput)] = &[
+    ("abs", CalculatorInput::Abs),
+    ("min", CalculatorInput::Min),
+    ("max", CalculatorInput::Max),
+];
+
+impl InfixOperator {
+    /// Left and right binding powers, as used by the Pratt loop.
+    fn binding_power(&self) -> (u8, u8) {
+        let power = self.precedence * 2;
+
+        match self.associativity {
+            Associativity::Left => (power, power + 1),
+            Associativity::Right => (power + 1, power),
+        }
+    }
+}
+
+pub(crate) fn infix_operator(symbol: &str) -> Option<&'static InfixOperator> {
+    INFIX_OPERATORS
+        .iter()
+        .find(|operator| operator.symbol == symbol)
+}
+
+pub(crate) fn infix_operator_for(input: &CalculatorInput) -> Option<&'static InfixOperator> {
+    INFIX_OPERATORS
+        .iter()
+        .find(|operator| operator.input == *input)
+}
+
+pub(crate) fn function(name: &str) -> Option<&'static CalculatorInput> {
+    FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .map(|(_, input)| input)
+}
+
+pub(crate) fn function_name(input: &CalculatorInput) -> Option<&'static str> {
+    FUNCTIONS
+        .iter()
+        .find(|(_, function)| function == input)
+        .map(|(name, _)| *name)
+}
+
+/// Whether a negative sign directly before a literal belongs to the
+/// literal, or to the operator expression that follows it.
+pub(crate) fn binds_literal_first(next: Option<&(Token, Span)>) -> bool {
+    match next {
+        Some((Token::Symbol(symbol), _)) => {
+            infix_operator(symbol).is_some_and(|operator| operator.precedence > PREFIX_PRECEDENCE)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Token<'a> {
+    Number(&'a str),
+    Identifier(&'a str),
+    Symbol(&'a str),
+}
+
+/// Longer symbols first, so `**` is not read as two `*`.
+const SYMBOLS: &[&str] = &[
+    "**", "<<", ">>", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "&", "|", "^", "<", ">",
+    "=", "(", ")", ",", ";",
+];
+
+/// Split infix text into numbers, identifiers and symbols. Unlike the RPN
+/// tokenizer, infix text does not need whitespace between tokens.
+pub(crate) fn scan(source: &str) -> Result<Vec<(Token<'_>, Span)>, LexError> {
+    let mut tokens = vec![];
+    let mut rest = source.char_indices().peekable();
+
+    while let Some((start, c)) = rest.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let mut end = start + 1;
+            while let Some(&(i, c)) = rest.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                rest.next();
+            }
+
+            let text = &source[start..end];
+            let token = if c.is_ascii_digit() {
+                Token::Number(text)
+            } else {
+                Token::Identifier(text)
+            };
+            tokens.push((token, start..end));
+        } else if let Some(symbol) = SYMBOLS
+            .iter()
+            .find(|symbol| source[start..].starts_with(**symbol))
+        {
+            let end = start + symbol.len();
+            for _ in 1..symbol.len() {
+                rest.next();
+            }
+            tokens.push((Token::Symbol(symbol), start..end));
+        } else {
+            let end = start + c.len_utf8();
+            return Err(LexError::UnknownSymbol {
+                symbol: c.to_string(),
+                span: start..end,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a numeric literal, negating it first so `-2147483648` fits.
+pub(crate) fn parse_number(literal: &str, negative: bool, span: Span) -> Result<i32, LexError> {
+    let text = if negative {
+        format!("-{}", literal)
+    } else {
+        literal.to_string()
+    };
+
+    text.parse().map_err(|_| LexError::InvalidNumber {
+        literal: text,
+        span,
+    })
+}
+
+/// Parse infix text such as `(4 + 8) / (7 - 5)` into calculator inputs
+/// in postfix order, ready to be passed to `evaluate`. Statements are
+/// separated by `;`, and `x = ...` stores a value in a variable.
+pub fn parse_infix(source: &str) -> Result<Vec<CalculatorInput>, ParseError> {
+    let statements = Parser::new(source)?.parse()?;
+
+    let mut output = vec![];
+    for statement in &statements {
+        statement.compile(&mut output);
+    }
+
+    Ok(output)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, Span)>,
+    position: usize,
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, LexError> {
+        Ok(Parser {
+            tokens: scan(source)?,
+            position: 0,
+            end: source.len(),
+        })
+    }
+
+    fn parse(mut self) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = vec![self.parse_statement()?];
+
+        loop {
+            match self.next() {
+                None => return Ok(statements),
+                Some((Token::Symbol(";"), _)) => statements.push(self.parse_statement()?),
+                Some((token, span)) => return Err(unexpected(token, span)),
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        if let (Some((Token::Identifier(name), _)), Some((Token::Symbol("="), _))) = (
+            self.tokens.get(self.position),
+            self.tokens.get(self.position + 1),
+        ) {
+            let name = name.to_string();
+            self.position += 2;
+            return Ok(Statement::Assign(name, self.parse_expression(0)?));
+        }
+
+        Ok(Statement::Expression(self.parse_expression(0)?))
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).map(|(token, _)| *token)
+    }
+
+    fn next(&mut self) -> Option<(Token<'a>, Span)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_next(&mut self) -> Result<(Token<'a>, Span), ParseError> {
+        self.next()
+            .ok_or(ParseError::UnexpectedEnd { at: self.end })
+    }
+
+    fn parse_expression(&mut self, min_binding_power: u8) -> Result<Expression, ParseError> {
+        let mut lhs = self.parse_prefix()?;
+
+        while let Some(Token::Symbol(symbol)) = self.peek() {
+            let operator = match infix_operator(symbol) {
+                Some(operator) => operator,
+                None => break,
+            };
+
+            let (left_power, right_power) = operator.binding_power();
+            if left_power < min_binding_power {
+                break;
+            }
+
+            self.next();
+            let rhs = self.parse_expression(right_power)?;
+            lhs = Expression::binary(operator.input.clone(), lhs, rhs);
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression, ParseError> {
+        match self.expect_next()? {
+            (Token::Number(literal), span) => {
+                Ok(Expression::Value(parse_number(literal, false, span)?))
+            }
+            (Token::Identifier(name), span) => match self.peek() {
+                Some(Token::Symbol("(")) => self.parse_call(name, span),
+                _ => Ok(Expression::Variable(name.to_string())),
+            },
+            (Token::Symbol("("), _) => {
+                let expression = self.parse_expression(0)?;
+                self.expect_symbol(")")?;
+                Ok(expression)
+            }
+            (Token::Symbol("-"), span) => self.parse_negation(span),
+            (token, span) => Err(unexpected(token, span)),
+        }
+    }
+
+    fn parse_negation(&mut self, minus: Span) -> Result<Expression, ParseError> {
+        if let Some((Token::Number(literal), span)) = self.tokens.get(self.position).cloned() {
+            if !binds_literal_first(self.tokens.get(self.position + 1)) {
+                self.next();
+                let value = parse_number(literal, true, minus.start..span.end)?;
+                return Ok(Expression::Value(value));
+            }
+        }
+
+        let operand = self.parse_expression(PREFIX_PRECEDENCE * 2)?;
+        Ok(Expression::unary(CalculatorInput::Negate, operand))
+    }
+
+    fn parse_call(&mut self, name: &str, span: Span) -> Result<Expression, ParseError> {
+        let input = function(name).ok_or_else(|| {
+            ParseError::Lex(LexError::UnknownSymbol {
+                symbol: name.to_string(),
+                span: span.clone(),
+            })
+        })?;
+
+        self.expect_symbol("(")?;
+        let mut arguments = vec![self.parse_expression(0)?];
+        while let Some(Token::Symbol(",")) = self.peek() {
+            self.next();
+            arguments.push(self.parse_expression(0)?);
+        }
+        let close = self.expect_symbol(")")?;
+
+        if arguments.len() != input.arity() {
+            return Err(ParseError::WrongArgumentCount {
+                function: name.to_string(),
+                expected: input.arity(),
+                found: arguments.len(),
+                span: span.start..close.end,
+            });
+        }
+
+        let mut arguments = arguments.into_iter();
+        let first = arguments.next().unwrap();
+        Ok(match arguments.next() {
+            Some(second) => Expression::binary(input.clone(), first, second),
+            None => Expression::unary(input.clone(), first),
+        })
+    }
+
+    fn expect_symbol(&mut self, expected: &str) -> Result<Span, ParseError> {
+        match self.expect_next()? {
+            (Token::Symbol(symbol), span) if symbol == expected => Ok(span),
+            (token, span) => Err(unexpected(token, span)),
+        }
+    }
+}
+
+pub(crate) fn unexpected(token: Token, span: Span) -> ParseError {
+    let token = match token {
+        Token::Number(text) | Token::Identifier(text) | Token::Symbol(text) => text.to_string(),
+    };
+
+    ParseError::UnexpectedToken { token, span }
+}
+
+#[cfg(test)]
+fn rpn(source: &str) -> Vec<CalculatorInput> {
+    crate::tokenize(source).unwrap()
+}
+
+#[test]
+fn test_parse_single_number() {
+    assert_eq!(parse_infix("42"), Ok(rpn("42")));
+}
+
+#[test]
+fn test_parse_respects_precedence() {
+    assert_eq!(parse_infix("1 + 2 * 3"), Ok(rpn("1 2 3 * +")));
+    assert_eq!(parse_infix("1 * 2 + 3"), Ok(rpn("1 2 * 3 +")));
+}
+
+#[test]
+fn test_parse_is_left_associative() {
+    assert_eq!(parse_infix("8 - 4 - 2"), Ok(rpn("8 4 - 2 -")));
+    assert_eq!(parse_infix("8 / 4 / 2"), Ok(rpn("8 4 / 2 /")));
+}
+
+#[test]
+fn test_parse_parentheses() {
+    assert_eq!(parse_infix("(4 + 8) / (7 - 5)"), Ok(rpn("4 8 + 7 5 - /")));
+    assert_eq!(parse_infix("8-(4-2)"), Ok(rpn("8 4 2 - -")));
+}
+
+#[test]
+fn test_parse_negative_literal() {
+    assert_eq!(parse_infix("-3 * -2"), Ok(rpn("-3 -2 *")));
+    assert_eq!(parse_infix("-2147483648"), Ok(rpn("-2147483648")));
+}
+
+#[test]
+fn test_parse_unary_minus_on_expression() {
+    assert_eq!(parse_infix("-(1 + 2)"), Ok(rpn("1 2 + neg")));
+    assert_eq!(parse_infix("2 - -(3)"), Ok(rpn("2 3 neg -")));
+}
+
+#[test]
+fn test_parse_and_evaluate() {
+    let inputs = parse_infix("(4 + 8) / (7 - 5)").unwrap();
+    assert_eq!(crate::evaluate(&inputs), Ok(6));
+}
+
+#[test]
+fn test_parse_unclosed_parenthesis() {
+    assert_eq!(
+        parse_infix("(1 + 2"),
+        Err(ParseError::UnexpectedEnd { at: 6 })
+    );
+}
+
+#[test]
+fn test_parse_unexpected_token() {
+    assert_eq!(
+        parse_infix("1 + * 2"),
+        Err(ParseError::UnexpectedToken {
+            token: "*".to_string(),
+            span: 4..5,
+        })
+    );
+    assert_eq!(
+        parse_infix("1 2"),
+        Err(ParseError::UnexpectedToken {
+            token: "2".to_string(),
+            span: 2..3,
+        })
+    );
+}
+
+#[test]
+fn test_parse_unknown_symbol() {
+    assert_eq!(
+        parse_infix("1 # 2"),
+        Err(ParseError::Lex(LexError::UnknownSymbol {
+            symbol: "#".to_string(),
+            span: 2..3,
+        }))
+    );
+}
+
+#[test]
+fn test_parse_power_is_right_associative() {
+    assert_eq!(parse_infix("2 ** 3 ** 2"), Ok(rpn("2 3 2 ** **")));
+    assert_eq!(parse_infix("(2 ** 3) ** 2"), Ok(rpn("2 3 ** 2 **")));
+}
+
+#[test]
+fn test_parse_power_binds_tighter_than_unary_minus() {
+    assert_eq!(parse_infix("-2 ** 2"), Ok(rpn("2 2 ** neg")));
+    assert_eq!(parse_infix("2 ** -2"), Ok(rpn("2 -2 **")));
+    assert_eq!(crate::evaluate(&parse_infix("-2 ** 2").unwrap()), Ok(-4));
+}
+
+#[test]
+fn test_parse_extended_operator_precedence() {
+    assert_eq!(parse_infix("1 + 2 << 3"), Ok(rpn("1 2 + 3 <<")));
+    assert_eq!(parse_infix("1 | 2 ^ 3 & 4"), Ok(rpn("1 2 3 4 & ^ |")));
+    assert_eq!(parse_infix("1 < 2 == 1"), Ok(rpn("1 2 < 1 ==")));
+    assert_eq!(parse_infix("7 % 4 * 2"), Ok(rpn("7 4 % 2 *")));
+}
+
+#[test]
+fn test_parse_function_calls() {
+    assert_eq!(parse_infix("abs(-3)"), Ok(rpn("-3 abs")));
+    assert_eq!(
+        parse_infix("max(1, min(2, 3)) * 2"),
+        Ok(rpn("1 2 3 min max 2 *"))
+    );
+}
+
+#[test]
+fn test_parse_variables_and_assignment() {
+    use CalculatorInput::*;
+
+    let x = || "x".to_string();
+    assert_eq!(
+        parse_infix("x = 2 * 3; x + 1"),
+        Ok(vec![
+            Value(2),
+            Value(3),
+            Multiply,
+            Store(x()),
+            Load(x()),
+            Value(1),
+            Add
+        ])
+    );
+    assert_eq!(parse_infix("abs"), Ok(vec![Load("abs".to_string())]));
+}
+
+#[test]
+fn test_parse_statement_errors() {
+    assert_eq!(
+        parse_infix("x = 1;"),
+        Err(ParseError::UnexpectedEnd { at: 6 })
+    );
+    assert_eq!(
+        parse_infix("1 = 2"),
+        Err(ParseError::UnexpectedToken {
+            token: "=".to_string(),
+            span: 2..3,
+        })
+    );
+}
+
+#[test]
+fn test_parse_function_call_errors() {
+    assert_eq!(
+        parse_infix("min(1)"),
+        Err(ParseError::WrongArgumentCount {
+            function: "min".to_string(),
+            expected: 2,
+            found: 1,
+            span: 0..6,
+        })
+    );
+    assert_eq!(
+        parse_infix("sqrt(4)"),
+        Err(ParseError::Lex(LexError::UnknownSymbol {
+            symbol: "sqrt".to_string(),
+            span: 0..4,
+        }))
+    );
+}
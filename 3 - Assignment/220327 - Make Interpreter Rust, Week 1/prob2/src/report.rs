@@ -0,0 +1,201 @@
+use crate::{
+    evaluate_located, tokenize_with_spans, CalculatorInput, EvalError, LexError, Options, Span,
+};
+use diagnostics::Diagnostic;
+
+/// Tokenize and evaluate RPN text such as `4 8 + 7 5 - /`. On failure the
+/// diagnostic points at the word that failed, ready to be rendered
+/// against `source`.
+pub fn evaluate_source(source: &str) -> Result<i32, Diagnostic> {
+    let (inputs, spans): (Vec<_>, Vec<_>) = tokenize_with_spans(source)
+        .map_err(|error| lex_diagnostic(&error))?
+        .into_iter()
+        .unzip();
+
+    evaluate_located(&inputs, Options::default()).map_err(|(error, at)| match at {
+        Some(at) => input_diagnostic(&error, &inputs[at], spans[at].clone()),
+        None => program_diagnostic(&error),
+    })
+}
+
+fn lex_diagnostic(error: &LexError) -> Diagnostic {
+    match error {
+        LexError::UnknownSymbol { symbol, span } => {
+            Diagnostic::error(format!("unknown symbol `{}`", symbol))
+                .with_label(span.clone(), "not a number, an operator or a variable")
+                .with_note("variables are stored with `name !` and loaded with `name @`")
+        }
+        LexError::InvalidNumber { literal, span } => {
+            Diagnostic::error(format!("invalid number `{}`", literal))
+                .with_label(span.clone(), "not an `i32`")
+        }
+    }
+}
+
+/// A diagnostic for an error raised by `input`, which is at `span`.
+fn input_diagnostic(error: &EvalError, input: &CalculatorInput, span: Span) -> Diagnostic {
+    match error {
+        EvalError::StackUnderflow { .. } => {
+            let arity = input.arity();
+            let operands = if arity == 1 { "operand" } else { "operands" };
+            Diagnostic::error("not enough operands")
+                .with_label(span, format!("`{}` takes {} {}", input, arity, operands))
+        }
+        EvalError::UnmatchedControl { .. } => {
+            let note = match input {
+                CalculatorInput::Begin | CalculatorInput::Until => "`begin` pairs with `until`",
+                _ => "`if` pairs with `then`, optionally with an `else` between them",
+            };
+            Diagnostic::error("unmatched control word")
+                .with_label(span, format!("this `{}` has no partner", input))
+                .with_note(note)
+        }
+        EvalError::DivisionByZero => {
+            Diagnostic::error(error.to_string()).with_label(span, "the divisor is 0")
+        }
+        EvalError::NegativeExponent => {
+            Diagnostic::error(error.to_string()).with_label(span, "the exponent is negative")
+        }
+        EvalError::Overflow => Diagnostic::error(error.to_string()).with_label(
+            span,
+            format!("the result of `{}` does not fit in an `i32`", input),
+        ),
+        EvalError::UnboundVariable(name) => Diagnostic::error(error.to_string())
+            .with_label(span, "loaded before anything is stored in it")
+            .with_note(format!("store a value first with `{} !`", name)),
+        EvalError::StepLimitExceeded => Diagnostic::error(error.to_string())
+            .with_label(span, "still running here")
+            .with_note("a `begin` ... `until` loop stops only when its condition is not 0"),
+        _ => Diagnostic::error(error.to_string()).with_label(span, ""),
+    }
+}
+
+/// A diagnostic for an error of the program as a whole, which has no
+/// input to point at.
+fn program_diagnostic(error: &EvalError) -> Diagnostic {
+    let diagnostic = Diagnostic::error(error.to_string());
+
+    match error {
+        EvalError::EmptyInput => diagnostic.with_note("enter RPN such as `4 8 + 7 5 - /`"),
+        EvalError::LeftoverOperands(_) => {
+            diagnostic.with_note("combine them with an operator, or `drop` the ones not needed")
+        }
+        _ => diagnostic,
+    }
+}
+
+#[cfg(test)]
+fn render(source: &str) -> String {
+    evaluate_source(source).unwrap_err().render(source)
+}
+
+#[test]
+fn test_evaluate_source() {
+    assert_eq!(evaluate_source("4 8 + 7 5 - /"), Ok(6));
+    assert_eq!(evaluate_source("6 x !\nx @ x @ *\n"), Ok(36));
+}
+
+#[test]
+fn test_lex_errors_point_at_the_word() {
+    assert_eq!(
+        render("1 2 ? +"),
+        "\
+error: unknown symbol `?`
+ --> 1:5
+  |
+1 | 1 2 ? +
+  |     ^ not a number, an operator or a variable
+  = note: variables are stored with `name !` and loaded with `name @`
+"
+    );
+    assert_eq!(
+        render("1\n99999999999 +"),
+        "\
+error: invalid number `99999999999`
+ --> 2:1
+  |
+2 | 99999999999 +
+  | ^^^^^^^^^^^ not an `i32`
+"
+    );
+}
+
+#[test]
+fn test_eval_errors_point_at_the_input() {
+    assert_eq!(
+        render("4 8 +\n7 0 /"),
+        "\
+error: division by zero
+ --> 2:5
+  |
+2 | 7 0 /
+  |     ^ the divisor is 0
+"
+    );
+    assert_eq!(
+        render("1 2 + * 3"),
+        "\
+error: not enough operands
+ --> 1:7
+  |
+1 | 1 2 + * 3
+  |       ^ `*` takes 2 operands
+"
+    );
+    assert_eq!(
+        render("2147483647 1 +"),
+        "\
+error: arithmetic overflow
+ --> 1:14
+  |
+1 | 2147483647 1 +
+  |              ^ the result of `+` does not fit in an `i32`
+"
+    );
+    assert_eq!(
+        render("1 rate @ +"),
+        "\
+error: unbound variable `rate`
+ --> 1:3
+  |
+1 | 1 rate @ +
+  |   ^^^^^^ loaded before anything is stored in it
+  = note: store a value first with `rate !`
+"
+    );
+    assert_eq!(
+        render("1 2 + then"),
+        "\
+error: unmatched control word
+ --> 1:7
+  |
+1 | 1 2 + then
+  |       ^^^^ this `then` has no partner
+  = note: `if` pairs with `then`, optionally with an `else` between them
+"
+    );
+}
+
+#[test]
+fn test_step_limit_points_into_the_loop() {
+    let report = render("1 begin 1 until begin 0 until");
+    assert!(
+        report.starts_with("error: step limit exceeded\n"),
+        "{}",
+        report
+    );
+    assert!(report.contains("still running here"), "{}", report);
+}
+
+#[test]
+fn test_program_errors_have_no_location() {
+    assert_eq!(
+        render("  "),
+        "error: nothing to evaluate\n  = note: enter RPN such as `4 8 + 7 5 - /`\n"
+    );
+    assert_eq!(
+        render("1 2"),
+        "error: 2 operands left on the stack: 1 2\n  \
+         = note: combine them with an operator, or `drop` the ones not needed\n"
+    );
+}
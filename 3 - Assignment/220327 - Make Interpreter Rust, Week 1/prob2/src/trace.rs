@@ -0,0 +1,163 @@
+use crate::control::{jump_targets, step, Steps};
+use crate::{get_result_from_stack, CalculatorInput, Environment, EvalError, Numeric, Options};
+use std::fmt;
+
+/// What one input did during `evaluate_traced`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep<N = i32> {
+    /// Position of the input in the program. Inputs inside a loop appear
+    /// once per pass.
+    pub at: usize,
+    pub input: CalculatorInput<N>,
+    /// Values taken off the stack, in the order they were pushed. `clear`
+    /// takes the whole stack.
+    pub popped: Vec<N>,
+    /// The stack after the input, bottom first.
+    pub stack: Vec<N>,
+}
+
+/// Shows the stack the way the README walkthrough does, e.g. `12 7 5`.
+impl<N: fmt::Display> fmt::Display for TraceStep<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut values = self.stack.iter();
+        if let Some(first) = values.next() {
+            write!(f, "{}", first)?;
+        }
+        for value in values {
+            write!(f, " {}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Same as `evaluate`, but also records a step for every input that ran.
+/// When evaluation fails, the trace ends with the last input that
+/// succeeded.
+pub fn evaluate_traced<N: Numeric>(
+    inputs: &[CalculatorInput<N>],
+) -> (Result<N, EvalError<N>>, Vec<TraceStep<N>>) {
+    let mut trace = Vec::with_capacity(inputs.len());
+    if inputs.is_empty() {
+        return (Err(EvalError::EmptyInput), trace);
+    }
+
+    let jumps = match jump_targets(inputs.iter().map(CalculatorInput::control)) {
+        Ok(jumps) => jumps,
+        Err(at) => return (Err(EvalError::UnmatchedControl { at }), trace),
+    };
+    let options = Options::default();
+    let mut steps = Steps::new(options.step_limit);
+    let mut stack: Vec<CalculatorInput<N>> = vec![];
+    let mut environment = Environment::new();
+
+    let mut pc = 0;
+    while let Some(input) = inputs.get(pc) {
+        let popped = match input {
+            CalculatorInput::Clear => values(&stack),
+            _ => values(&stack[stack.len().saturating_sub(input.arity())..]),
+        };
+
+        let next = steps.take().and_then(|()| {
+            step(
+                &mut stack,
+                &mut environment,
+                &options,
+                &jumps,
+                pc,
+                pc,
+                input,
+            )
+        });
+        match next {
+            Ok(next) => {
+                trace.push(TraceStep {
+                    at: pc,
+                    input: input.clone(),
+                    popped,
+                    stack: values(&stack),
+                });
+                pc = next;
+            }
+            Err(error) => return (Err(error), trace),
+        }
+    }
+
+    (get_result_from_stack(&stack), trace)
+}
+
+fn values<N: Clone>(stack: &[CalculatorInput<N>]) -> Vec<N> {
+    stack
+        .iter()
+        .filter_map(|input| match input {
+            CalculatorInput::Value(n) => Some(n.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_trace_matches_readme_walkthrough() {
+    let (result, trace) = evaluate_traced(&crate::tokenize("4 8 + 7 5 - /").unwrap());
+    let stacks: Vec<String> = trace.iter().map(TraceStep::to_string).collect();
+
+    assert_eq!(result, Ok(6));
+    assert_eq!(stacks, ["4", "4 8", "12", "12 7", "12 7 5", "12 2", "6"]);
+    assert_eq!(
+        trace[2],
+        TraceStep {
+            at: 2,
+            input: CalculatorInput::Add,
+            popped: vec![4, 8],
+            stack: vec![12],
+        }
+    );
+    assert_eq!(trace[6].popped, vec![12, 2]);
+}
+
+#[test]
+fn test_trace_records_stack_words() {
+    let (result, trace) = evaluate_traced(&crate::tokenize("1 2 3 rot clear 4 x ! x @").unwrap());
+
+    assert_eq!(result, Ok(4));
+    assert_eq!(trace[3].popped, vec![1, 2, 3]);
+    assert_eq!(trace[3].stack, vec![2, 3, 1]);
+    assert_eq!(trace[4].popped, vec![2, 3, 1]);
+    assert!(trace[4].stack.is_empty());
+    assert_eq!(trace[6].popped, vec![4]);
+    assert!(trace[7].popped.is_empty());
+}
+
+#[test]
+fn test_trace_stops_before_failing_input() {
+    let inputs = crate::tokenize("1 2 + 0 / 5").unwrap();
+    let (result, trace) = evaluate_traced(&inputs);
+
+    assert_eq!(result, Err(EvalError::DivisionByZero));
+    assert_eq!(trace.len(), 4);
+    assert_eq!(trace[3].at, 3);
+    assert_eq!(trace[3].to_string(), "3 0");
+
+    let (result, trace) = evaluate_traced(&crate::tokenize("2 2").unwrap());
+    assert_eq!(result, Err(EvalError::LeftoverOperands(vec![2, 2])));
+    assert_eq!(trace.len(), 2);
+
+    let (result, trace) = evaluate_traced::<i32>(&[]);
+    assert_eq!(result, Err(EvalError::EmptyInput));
+    assert!(trace.is_empty());
+}
+
+#[test]
+fn test_trace_follows_jumps() {
+    let (result, trace) = evaluate_traced(&crate::tokenize("0 begin 1 + dup 2 == until").unwrap());
+    let positions: Vec<usize> = trace.iter().map(|step| step.at).collect();
+
+    assert_eq!(result, Ok(2));
+    assert_eq!(positions, [0, 1, 2, 3, 4, 5, 6, 7, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(trace[7].popped, vec![0]);
+    assert_eq!(trace[7].stack, vec![1]);
+
+    let (result, trace) = evaluate_traced(&crate::tokenize("0 if 1 else 2 then").unwrap());
+    let positions: Vec<usize> = trace.iter().map(|step| step.at).collect();
+    assert_eq!(result, Ok(2));
+    assert_eq!(positions, [0, 1, 4, 5]);
+}
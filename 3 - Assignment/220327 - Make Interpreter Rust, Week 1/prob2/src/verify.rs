@@ -0,0 +1,187 @@
+use crate::control::{jump_targets, Control};
+use crate::operation::Operation;
+use crate::{CalculatorInput, Numeric};
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    EmptyInput,
+    EmptyStack,
+    StackUnderflow { at: usize },
+    LeftoverOperands { count: usize },
+    UnmatchedControl { at: usize },
+    UnbalancedBranches { at: usize },
+    UnbalancedLoop { at: usize },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::EmptyInput => write!(f, "nothing to evaluate"),
+            VerifyError::EmptyStack => write!(f, "no result would be left on the stack"),
+            VerifyError::StackUnderflow { at } => {
+                write!(f, "not enough operands for input {}", at + 1)
+            }
+            VerifyError::LeftoverOperands { count } => {
+                write!(f, "{} operands would be left on the stack", count)
+            }
+            VerifyError::UnmatchedControl { at } => {
+                write!(f, "unmatched control word at input {}", at + 1)
+            }
+            VerifyError::UnbalancedBranches { at } => {
+                write!(
+                    f,
+                    "branches ending at input {} leave different stacks",
+                    at + 1
+                )
+            }
+            VerifyError::UnbalancedLoop { at } => {
+                write!(f, "loop ending at input {} changes the stack depth", at + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Check the stack effect of every input without running any of them, and
+/// return the maximum stack depth reached. Both branches of an `if` must
+/// leave the stack equally deep, and so must every pass through a loop.
+/// A program that passes can only fail in `evaluate` on its values, such
+/// as a division by zero or an unbound variable, or by running out of
+/// steps, never on the shape of the stack.
+pub fn verify<N: Numeric>(inputs: &[CalculatorInput<N>]) -> Result<usize, VerifyError> {
+    if inputs.is_empty() {
+        return Err(VerifyError::EmptyInput);
+    }
+
+    jump_targets(inputs.iter().map(CalculatorInput::control))
+        .map_err(|at| VerifyError::UnmatchedControl { at })?;
+
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    // The depth every open `if` or `begin` body starts at, and for an `if`
+    // past its `else`, the depth its first branch ended at.
+    let mut open: Vec<(usize, Option<usize>)> = vec![];
+
+    for (at, input) in inputs.iter().enumerate() {
+        let operation = input.operation();
+        depth = match operation {
+            Operation::Clear => 0,
+            _ => {
+                depth
+                    .checked_sub(operation.arity())
+                    .ok_or(VerifyError::StackUnderflow { at })?
+                    + operation.pushes()
+            }
+        };
+        max_depth = max_depth.max(depth);
+
+        // `jump_targets` has checked that every control word is paired.
+        match input.control() {
+            Some(Control::If | Control::Begin) => open.push((depth, None)),
+            Some(Control::Else) => {
+                let (start, _) = open.pop().unwrap();
+                open.push((start, Some(depth)));
+                depth = start;
+            }
+            Some(Control::Then) => {
+                let (start, branch) = open.pop().unwrap();
+                if depth != branch.unwrap_or(start) {
+                    return Err(VerifyError::UnbalancedBranches { at });
+                }
+            }
+            Some(Control::Until) => {
+                let (start, _) = open.pop().unwrap();
+                if depth != start {
+                    return Err(VerifyError::UnbalancedLoop { at });
+                }
+            }
+            None => {}
+        }
+    }
+
+    match depth {
+        0 => Err(VerifyError::EmptyStack),
+        1 => Ok(max_depth),
+        count => Err(VerifyError::LeftoverOperands { count }),
+    }
+}
+
+#[cfg(test)]
+fn verify_source(source: &str) -> Result<usize, VerifyError> {
+    verify(&crate::tokenize(source).unwrap())
+}
+
+#[test]
+fn test_verify_reports_max_depth() {
+    assert_eq!(verify_source("10"), Ok(1));
+    assert_eq!(verify_source("4 8 + 7 5 - /"), Ok(3));
+    assert_eq!(verify_source("1 2 3 4 + + +"), Ok(4));
+    assert_eq!(verify_source("1 2 over rot * +"), Ok(3));
+    assert_eq!(verify_source("6 x ! x @ x @ *"), Ok(2));
+}
+
+#[test]
+fn test_verify_rejects_malformed_programs() {
+    assert_eq!(verify_source(""), Err(VerifyError::EmptyInput));
+    assert_eq!(
+        verify_source("1 2 + * 3"),
+        Err(VerifyError::StackUnderflow { at: 3 })
+    );
+    assert_eq!(
+        verify_source("1 2 3 rot"),
+        Err(VerifyError::LeftoverOperands { count: 3 })
+    );
+    assert_eq!(verify_source("1 drop"), Err(VerifyError::EmptyStack));
+    assert_eq!(verify_source("1 x !"), Err(VerifyError::EmptyStack));
+}
+
+#[test]
+fn test_verify_clear_resets_depth() {
+    assert_eq!(verify_source("1 2 3 clear 4"), Ok(3));
+    assert_eq!(
+        verify_source("1 2 clear +"),
+        Err(VerifyError::StackUnderflow { at: 3 })
+    );
+}
+
+#[test]
+fn test_verify_ignores_values() {
+    assert_eq!(verify_source("1 0 /"), Ok(2));
+    assert_eq!(verify_source("y @"), Ok(1));
+}
+
+#[test]
+fn test_verify_control_flow() {
+    assert_eq!(verify_source("1 if 10 else 20 30 + then"), Ok(2));
+    assert_eq!(verify_source("5 dup 0 < if neg then"), Ok(3));
+    assert_eq!(
+        verify_source("5 1 begin over * swap 1 - swap over 1 <= until swap drop"),
+        Ok(4)
+    );
+    assert_eq!(
+        verify_source("1 if 2 then"),
+        Err(VerifyError::UnbalancedBranches { at: 3 })
+    );
+    assert_eq!(
+        verify_source("1 if 2 else 3 4 then"),
+        Err(VerifyError::UnbalancedBranches { at: 6 })
+    );
+    assert_eq!(
+        verify_source("0 begin 1 dup until"),
+        Err(VerifyError::UnbalancedLoop { at: 4 })
+    );
+    assert_eq!(
+        verify_source("1 begin drop 1 until"),
+        Err(VerifyError::UnbalancedLoop { at: 4 })
+    );
+    assert_eq!(
+        verify_source("if 1 then"),
+        Err(VerifyError::StackUnderflow { at: 0 })
+    );
+    assert_eq!(
+        verify_source("1 if 2"),
+        Err(VerifyError::UnmatchedControl { at: 1 })
+    );
+}
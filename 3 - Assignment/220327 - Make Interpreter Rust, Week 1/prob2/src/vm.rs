@@ -0,0 +1,268 @@
+use crate::bytecode::{Opcode, Program, OPERAND_LEN};
+use crate::control::Steps;
+use crate::ArithmeticMode::Checked;
+use crate::{Division, EvalError, Numeric, Options};
+
+/// Runs compiled programs. The stack and variable slots are kept between
+/// runs, so evaluating the same program many times does not allocate.
+#[derive(Debug)]
+pub struct Vm {
+    stack: Vec<i32>,
+    /// The value of each variable, or `None` until it is stored, which
+    /// may never happen if the store is on a branch that is not taken.
+    slots: Vec<Option<i32>>,
+    step_limit: usize,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Vm::with_step_limit(Options::default().step_limit)
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A VM that fails with `StepLimitExceeded` after running
+    /// `step_limit` instructions.
+    pub fn with_step_limit(step_limit: usize) -> Self {
+        Vm {
+            stack: vec![],
+            slots: vec![],
+            step_limit,
+        }
+    }
+
+    /// Run `program` with `arguments` as the values of its parameters.
+    /// Gives the same result as `evaluate` on the inputs it was compiled
+    /// from.
+    pub fn run(&mut self, program: &Program, arguments: &[i32]) -> Result<i32, EvalError> {
+        if arguments.len() != program.parameters {
+            return Err(EvalError::ArgumentCount {
+                expected: program.parameters,
+                found: arguments.len(),
+            });
+        }
+        if program.code.is_empty() {
+            return Err(EvalError::EmptyInput);
+        }
+
+        let code = &program.code[..];
+        let stack = &mut self.stack;
+        stack.clear();
+        self.slots.clear();
+        self.slots.extend(arguments.iter().copied().map(Some));
+        self.slots.resize(program.variables.len(), None);
+
+        let mut steps = Steps::new(self.step_limit);
+        let mut pc = 0;
+        while pc < code.len() {
+            steps.take()?;
+            let opcode = Opcode::from_byte(code[pc]);
+            if stack.len() < opcode.arity() {
+                return Err(EvalError::StackUnderflow {
+                    at: program.input_at(pc),
+                });
+            }
+            pc += 1;
+
+            match opcode {
+                Opcode::Push => {
+                    let index = read_operand(code, &mut pc);
+                    stack.push(program.constants[index]);
+                }
+                Opcode::Load => {
+                    let slot = read_operand(code, &mut pc);
+                    let value = self.slots[slot].ok_or_else(|| {
+                        EvalError::UnboundVariable(program.variables[slot].clone())
+                    })?;
+                    stack.push(value);
+                }
+                Opcode::Store => {
+                    let slot = read_operand(code, &mut pc);
+                    self.slots[slot] = Some(stack.pop().unwrap());
+                }
+
+                Opcode::Negate => unary(stack, |a| a.negate(Checked))?,
+                Opcode::Abs => unary(stack, |a| Numeric::abs(a, Checked))?,
+
+                Opcode::Add => binary(stack, |a, b| a.add(b, Checked))?,
+                Opcode::Subtract => binary(stack, |a, b| a.subtract(b, Checked))?,
+                Opcode::Multiply => binary(stack, |a, b| a.multiply(b, Checked))?,
+                Opcode::Divide => binary(stack, |a, b| a.divide(b, Division::Truncating, Checked))?,
+                Opcode::Modulo => binary(stack, |a, b| a.modulo(b, Checked))?,
+                Opcode::Power => binary(stack, |a, b| a.power(b, Checked))?,
+
+                Opcode::And => binary(stack, Numeric::and)?,
+                Opcode::Or => binary(stack, Numeric::or)?,
+                Opcode::Xor => binary(stack, Numeric::xor)?,
+                Opcode::Shl => binary(stack, Numeric::shl)?,
+                Opcode::Shr => binary(stack, Numeric::shr)?,
+
+                Opcode::Equal => binary(stack, |a, b| Ok((a == b) as i32))?,
+                Opcode::NotEqual => binary(stack, |a, b| Ok((a != b) as i32))?,
+                Opcode::Less => binary(stack, |a, b| Ok((a < b) as i32))?,
+                Opcode::LessEqual => binary(stack, |a, b| Ok((a <= b) as i32))?,
+                Opcode::Greater => binary(stack, |a, b| Ok((a > b) as i32))?,
+                Opcode::GreaterEqual => binary(stack, |a, b| Ok((a >= b) as i32))?,
+
+                Opcode::Min => binary(stack, |a, b| Ok(a.min(b)))?,
+                Opcode::Max => binary(stack, |a, b| Ok(a.max(b)))?,
+
+                Opcode::Dup => stack.push(stack[stack.len() - 1]),
+                Opcode::Drop => {
+                    stack.pop();
+                }
+                Opcode::Swap => {
+                    let top = stack.len() - 1;
+                    stack.swap(top - 1, top);
+                }
+                Opcode::Over => stack.push(stack[stack.len() - 2]),
+                Opcode::Rot => {
+                    let start = stack.len() - 3;
+                    stack[start..].rotate_left(1);
+                }
+                Opcode::Clear => stack.clear(),
+
+                Opcode::Jump => pc = read_operand(code, &mut pc),
+                Opcode::JumpIfZero => {
+                    let target = read_operand(code, &mut pc);
+                    if stack.pop().unwrap() == 0 {
+                        pc = target;
+                    }
+                }
+            }
+        }
+
+        match stack[..] {
+            [] => Err(EvalError::EmptyStack),
+            [result] => Ok(result),
+            _ => Err(EvalError::LeftoverOperands(stack.clone())),
+        }
+    }
+}
+
+fn read_operand(code: &[u8], pc: &mut usize) -> usize {
+    let bytes = code[*pc..*pc + OPERAND_LEN].try_into().unwrap();
+    *pc += OPERAND_LEN;
+    u32::from_le_bytes(bytes) as usize
+}
+
+#[inline(always)]
+fn unary(
+    stack: &mut [i32],
+    operator: impl Fn(i32) -> Result<i32, EvalError>,
+) -> Result<(), EvalError> {
+    let a = stack.last_mut().unwrap();
+    *a = operator(*a)?;
+    Ok(())
+}
+
+#[inline(always)]
+fn binary(
+    stack: &mut Vec<i32>,
+    operator: impl Fn(i32, i32) -> Result<i32, EvalError>,
+) -> Result<(), EvalError> {
+    let b = stack.pop().unwrap();
+    let a = stack.last_mut().unwrap();
+    *a = operator(*a, b)?;
+    Ok(())
+}
+
+#[cfg(test)]
+fn run(source: &str, arguments: &[i32]) -> Result<i32, EvalError> {
+    let program = crate::compile(&crate::tokenize(source).unwrap())?;
+    Vm::new().run(&program, arguments)
+}
+
+#[test]
+fn test_vm_matches_evaluate() {
+    for source in [
+        "",
+        "10",
+        "4 8 + 7 5 - /",
+        "2 +",
+        "1 2 + * 3",
+        "2 2",
+        "1 0 /",
+        "2147483647 1 +",
+        "-2147483648 -1 /",
+        "17 -5 % 3 -4 min max abs",
+        "2 3 2 ** ** 1 4 << | 12 10 ^ &",
+        "3 4 < 4 4 >= + 1 2 == - 1 2 != +",
+        "1 2 3 rot drop swap over - * dup +",
+        "1 2 clear",
+        "1 2 3 clear 4 neg",
+        "6 x ! x @ x @ *",
+        "x !",
+        "1 x ! x !",
+        "2 -1 **",
+        "1 32 <<",
+        "3 dup 0 < if neg else dup 0 == if drop 7 then then",
+        "-3 dup 0 < if neg else dup 0 == if drop 7 then then",
+        "0 dup 0 < if neg else dup 0 == if drop 7 then then",
+        "5 1 begin over * swap 1 - swap over 1 <= until swap drop",
+        "1 n ! begin n @ 2 * n ! n @ 1000 > until n @",
+        "0 if 5 t ! then t @",
+        "1 if 5 t ! then t @",
+        "0 if 5 t ! else 6 u ! then u @",
+        "1 if 5 t ! else 6 u ! then u @",
+        "begin 0 until",
+        "1 if 2",
+        "if 1 then",
+        "1 2 else",
+    ] {
+        let inputs = crate::tokenize(source).unwrap();
+        assert_eq!(
+            crate::compile(&inputs).and_then(|program| Vm::new().run(&program, &[])),
+            crate::evaluate(&inputs),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_vm_reads_arguments() {
+    let mut vm = Vm::new();
+    let program = crate::compile(&crate::tokenize("x @ x @ * y @ -").unwrap()).unwrap();
+
+    assert_eq!(vm.run(&program, &[3, 1]), Ok(8));
+    assert_eq!(vm.run(&program, &[5, 5]), Ok(20));
+}
+
+#[test]
+fn test_vm_checks_argument_count() {
+    assert_eq!(
+        run("x @ 1 +", &[]),
+        Err(EvalError::ArgumentCount {
+            expected: 1,
+            found: 0,
+        })
+    );
+    assert_eq!(
+        run("1", &[1]),
+        Err(EvalError::ArgumentCount {
+            expected: 0,
+            found: 1,
+        })
+    );
+}
+
+#[test]
+fn test_vm_variables_written_before_read_are_locals() {
+    assert_eq!(run("2 t ! t @ x @ * t !  t @ 1 +", &[10]), Ok(21));
+}
+
+#[test]
+fn test_vm_step_limit() {
+    let program = crate::compile(&crate::tokenize("0 begin 1 + dup 10 == until").unwrap()).unwrap();
+
+    assert_eq!(Vm::with_step_limit(61).run(&program, &[]), Ok(10));
+    assert_eq!(
+        Vm::with_step_limit(60).run(&program, &[]),
+        Err(EvalError::StepLimitExceeded)
+    );
+}
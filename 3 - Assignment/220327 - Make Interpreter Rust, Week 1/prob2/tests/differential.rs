@@ -0,0 +1,209 @@
+//! Compares this crate's `evaluate`, the bytecode VM and the Week 1
+//! solution against a small reference model, on programs built from the
+//! four operators all of them support.
+
+use prob2::{compile, evaluate, CalculatorInput, EvalError, Vm};
+use proptest::prelude::*;
+
+/// The solution is a package with the same name as this one, so Cargo
+/// cannot depend on both. Its tests run again as part of this file.
+#[path = "../../../../4 - Solution/220327 - Make Interpreter Rust, Week 1/prob2/src/lib.rs"]
+mod solution;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Value(i32),
+}
+
+/// Why a program has no result. The implementations report malformed
+/// programs in different detail, so the model only tells them apart from
+/// arithmetic failures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fault {
+    Malformed,
+    DivisionByZero,
+    Overflow,
+}
+
+/// Evaluate with a stack of `i64`, which holds every sum, difference,
+/// product and quotient of two `i32`, and fail when a result does not fit
+/// back into an `i32`.
+fn model(ops: &[Op]) -> Result<i32, Fault> {
+    let mut stack: Vec<i64> = vec![];
+
+    for op in ops {
+        let (a, b) = match op {
+            Op::Value(n) => {
+                stack.push(i64::from(*n));
+                continue;
+            }
+            _ if stack.len() < 2 => return Err(Fault::Malformed),
+            _ => {
+                let b = stack.pop().unwrap();
+                (stack.pop().unwrap(), b)
+            }
+        };
+        let result = match op {
+            Op::Add => a + b,
+            Op::Subtract => a - b,
+            Op::Multiply => a * b,
+            Op::Divide if b == 0 => return Err(Fault::DivisionByZero),
+            Op::Divide => a / b,
+            Op::Value(_) => unreachable!(),
+        };
+        if i32::try_from(result).is_err() {
+            return Err(Fault::Overflow);
+        }
+        stack.push(result);
+    }
+
+    match stack[..] {
+        [result] => Ok(result as i32),
+        _ => Err(Fault::Malformed),
+    }
+}
+
+fn fault(error: EvalError) -> Fault {
+    match error {
+        EvalError::DivisionByZero => Fault::DivisionByZero,
+        EvalError::Overflow => Fault::Overflow,
+        EvalError::EmptyInput
+        | EvalError::EmptyStack
+        | EvalError::StackUnderflow { .. }
+        | EvalError::LeftoverOperands(_) => Fault::Malformed,
+        error => panic!("{:?} cannot come from the four operators", error),
+    }
+}
+
+fn inputs(ops: &[Op]) -> Vec<CalculatorInput> {
+    ops.iter()
+        .map(|op| match *op {
+            Op::Add => CalculatorInput::Add,
+            Op::Subtract => CalculatorInput::Subtract,
+            Op::Multiply => CalculatorInput::Multiply,
+            Op::Divide => CalculatorInput::Divide,
+            Op::Value(n) => CalculatorInput::Value(n),
+        })
+        .collect()
+}
+
+fn solution_inputs(ops: &[Op]) -> Vec<solution::CalculatorInput> {
+    ops.iter()
+        .map(|op| match *op {
+            Op::Add => solution::CalculatorInput::Add,
+            Op::Subtract => solution::CalculatorInput::Subtract,
+            Op::Multiply => solution::CalculatorInput::Multiply,
+            Op::Divide => solution::CalculatorInput::Divide,
+            Op::Value(n) => solution::CalculatorInput::Value(n),
+        })
+        .collect()
+}
+
+fn check(ops: &[Op]) -> Result<(), TestCaseError> {
+    let expected = model(ops);
+    let inputs = inputs(ops);
+
+    prop_assert_eq!(evaluate(&inputs).map_err(fault), expected);
+    prop_assert_eq!(
+        compile(&inputs)
+            .and_then(|program| Vm::new().run(&program, &[]))
+            .map_err(fault),
+        expected
+    );
+    // The solution uses plain operators, which panic on these.
+    if !matches!(expected, Err(Fault::DivisionByZero | Fault::Overflow)) {
+        prop_assert_eq!(solution::evaluate(&solution_inputs(ops)), expected.ok());
+    }
+    Ok(())
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => prop_oneof![
+            Just(0),
+            Just(1),
+            Just(-1),
+            Just(i32::MIN),
+            Just(i32::MAX),
+            -100..100,
+            any::<i32>(),
+        ]
+        .prop_map(Op::Value),
+        2 => prop::sample::select(vec![Op::Add, Op::Subtract, Op::Multiply, Op::Divide]),
+    ]
+}
+
+/// Drop the operators that would underflow and combine whatever is left
+/// on the stack at the end, so that the program has a result unless the
+/// arithmetic fails.
+fn well_formed(ops: Vec<Op>) -> Vec<Op> {
+    let mut output = vec![];
+    let mut depth = 0;
+    for op in ops {
+        match op {
+            Op::Value(_) => depth += 1,
+            _ if depth < 2 => continue,
+            _ => depth -= 1,
+        }
+        output.push(op);
+    }
+    if depth == 0 {
+        output.push(Op::Value(0));
+    }
+    output.extend((1..depth).map(|_| Op::Add));
+    output
+}
+
+#[test]
+fn test_model_agrees_on_the_hand_written_cases() {
+    use Op::*;
+
+    assert_eq!(model(&[]), Err(Fault::Malformed));
+    assert_eq!(model(&[Value(10)]), Ok(10));
+    assert_eq!(model(&[Value(7), Value(11), Subtract]), Ok(-4));
+    assert_eq!(model(&[Value(57), Value(19), Divide]), Ok(3));
+    assert_eq!(
+        model(&[
+            Value(4),
+            Value(8),
+            Add,
+            Value(7),
+            Value(5),
+            Subtract,
+            Divide
+        ]),
+        Ok(6)
+    );
+    assert_eq!(model(&[Value(2), Add]), Err(Fault::Malformed));
+    assert_eq!(model(&[Value(2), Value(2)]), Err(Fault::Malformed));
+    assert_eq!(
+        model(&[Add, Value(2), Value(2), Multiply]),
+        Err(Fault::Malformed)
+    );
+    assert_eq!(
+        model(&[Value(1), Value(0), Divide]),
+        Err(Fault::DivisionByZero)
+    );
+    assert_eq!(
+        model(&[Value(i32::MIN), Value(-1), Divide]),
+        Err(Fault::Overflow)
+    );
+}
+
+proptest! {
+    #[test]
+    fn implementations_match_the_model(ops in prop::collection::vec(op(), 0..24)) {
+        check(&ops)?;
+    }
+
+    #[test]
+    fn implementations_match_the_model_on_well_formed_programs(
+        ops in prop::collection::vec(op(), 0..24).prop_map(well_formed),
+    ) {
+        check(&ops)?;
+    }
+}
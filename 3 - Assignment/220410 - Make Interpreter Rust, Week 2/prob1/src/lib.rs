@@ -1,5 +1,7 @@
+use diagnostics::Diagnostic;
 use regex::Regex;
 use std::collections::HashMap;
+use std::ops::Range;
 
 /// Count occurrences of words.
 pub fn word_count_functional(words: &str) -> HashMap<String, u32> {
@@ -22,6 +24,27 @@ pub fn word_count_functional(words: &str) -> HashMap<String, u32> {
 
 pub fn word_count(words: &str) -> HashMap<String, u32> {
     let mut hashmap: HashMap<String, u32> = HashMap::new();
+
+    for word in split_words(words) {
+        let entry = hashmap.entry(normalize(word)).or_insert(0u32);
+        *entry += 1;
+    }
+
+    hashmap
+}
+
+/// Split text into the words `word_count` counts, in order and with their
+/// case kept.
+pub fn split_words(words: &str) -> Vec<&str> {
+    word_spans(words)
+        .into_iter()
+        .map(|span| &words[span])
+        .collect()
+}
+
+/// Byte span of every word `split_words` returns.
+pub fn word_spans(words: &str) -> Vec<Range<usize>> {
+    let mut result = vec![];
     let mut start: Option<usize> = None;
     let mut quote: Option<usize> = None;
 
@@ -36,21 +59,52 @@ pub fn word_count(words: &str) -> HashMap<String, u32> {
         } else if c == '\'' && is_next_alphanumeric && start.is_some() && quote.is_none() {
             quote = Some(i);
         } else if start.is_some() && !c.is_alphanumeric() {
-            let word = words[start.unwrap()..i].to_lowercase().to_string();
-            let entry = hashmap.entry(word).or_insert(0u32);
-            *entry += 1;
+            result.push(start.unwrap()..i);
 
             (start, quote) = (None, None);
         }
     }
 
-    if start.is_some() {
-        let key = words[start.unwrap()..].to_lowercase().to_string();
-        let entry = hashmap.entry(key).or_insert(0u32);
-        *entry += 1;
+    if let Some(start) = start {
+        result.push(start..words.len());
     }
 
-    hashmap
+    result
+}
+
+/// A note pointing at every place `word` is counted in `words`, to show
+/// where the count `word_count` gives it comes from.
+pub fn locate_word(words: &str, word: &str) -> Diagnostic {
+    let word = normalize(word);
+    let spans: Vec<Range<usize>> = word_spans(words)
+        .into_iter()
+        .filter(|span| normalize(&words[span.clone()]) == word)
+        .collect();
+
+    let mut diagnostic = match spans.len() {
+        0 => {
+            return Diagnostic::note(format!("`{}` is not counted", word)).with_note(
+                "words are compared in lower case, and punctuation is not part of a word",
+            )
+        }
+        1 => Diagnostic::note(format!("`{}` is counted once", word)),
+        count => Diagnostic::note(format!("`{}` is counted {} times", word, count)),
+    };
+    for (index, span) in spans.into_iter().enumerate() {
+        diagnostic = if index == 0 {
+            diagnostic.with_label(span, "counted here")
+        } else {
+            diagnostic.with_secondary_label(span, "and here")
+        };
+    }
+
+    diagnostic
+}
+
+/// The form of a word that `word_count` counts it under, so that "Go" and
+/// "GO" are the same word.
+pub fn normalize(word: &str) -> String {
+    word.to_lowercase()
 }
 
 pub fn word_count_regex(words: &str) -> HashMap<String, u32> {
@@ -82,6 +136,40 @@ fn check_word_count(s: &str, pairs: &[(&str, u32)]) {
     assert_eq!(m.iter().collect::<Vec<(&String, &u32)>>(), vec![]);
 }
 
+#[test]
+fn test_split_words_keeps_order_and_case() {
+    assert_eq!(
+        split_words("Joe can't tell between 'large' and Large."),
+        vec!["Joe", "can't", "tell", "between", "large", "and", "Large"]
+    );
+    assert_eq!(normalize("Large"), "large");
+}
+
+#[test]
+fn test_locate_word() {
+    let text = "Go, go!\nStop.\n\n\nGO";
+
+    assert_eq!(
+        locate_word(text, "GO").render(text),
+        "\
+note: `go` is counted 3 times
+ --> 1:1
+  |
+1 | Go, go!
+  | ^^ counted here
+  |     -- and here
+...
+5 | GO
+  | -- and here
+"
+    );
+    assert_eq!(
+        locate_word(text, "stop").render(text),
+        "note: `stop` is counted once\n --> 2:1\n  |\n2 | Stop.\n  | ^^^^ counted here\n"
+    );
+    assert_eq!(locate_word(text, "Go,").message, "`go,` is not counted");
+}
+
 #[test]
 fn test_count_one_word() {
     check_word_count("word", &[("word", 1)]);
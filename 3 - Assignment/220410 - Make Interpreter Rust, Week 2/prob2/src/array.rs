@@ -0,0 +1,151 @@
+//! A circular buffer whose capacity is part of its type, for targets
+//! without an allocator.
+
+use crate::ring::Ring;
+use crate::Error;
+use core::mem::MaybeUninit;
+#[cfg(test)]
+use std::rc::Rc;
+
+/// A first-in, first-out queue of at most `N` elements, stored inline.
+///
+/// It never allocates, and `new` is a `const fn`, so the buffer can live
+/// on the stack or in a `static`:
+///
+/// ```
+/// use prob2::array::CircularBuffer;
+/// use std::sync::Mutex;
+///
+/// static LOG: Mutex<CircularBuffer<&str, 64>> = Mutex::new(CircularBuffer::new());
+///
+/// LOG.lock().unwrap().overwrite("booted");
+/// assert_eq!(LOG.lock().unwrap().read(), Ok("booted"));
+/// ```
+pub struct CircularBuffer<T, const N: usize> {
+    ring: Ring<T, [MaybeUninit<T>; N]>,
+}
+
+impl<T, const N: usize> CircularBuffer<T, N> {
+    pub const fn new() -> Self {
+        CircularBuffer {
+            ring: Ring::new([const { MaybeUninit::uninit() }; N]),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.ring.is_full()
+    }
+
+    pub fn write(&mut self, element: T) -> Result<(), Error> {
+        self.ring.write(element)
+    }
+
+    pub fn read(&mut self) -> Result<T, Error> {
+        self.ring.read()
+    }
+
+    pub fn clear(&mut self) {
+        self.ring.clear();
+    }
+
+    /// Write `element`, making room by dropping the oldest element if the
+    /// buffer is full. With `N` of 0, `element` is dropped.
+    pub fn overwrite(&mut self, element: T) {
+        self.ring.overwrite(element);
+    }
+}
+
+impl<T, const N: usize> Default for CircularBuffer<T, N> {
+    fn default() -> Self {
+        CircularBuffer::new()
+    }
+}
+
+#[test]
+fn error_on_read_empty_buffer() {
+    let mut buffer = CircularBuffer::<char, 1>::new();
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn full_buffer_cant_be_written_to() {
+    let mut buffer = CircularBuffer::<_, 1>::new();
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Err(Error::FullBuffer), buffer.write('2'));
+}
+
+#[test]
+fn read_position_is_maintained_even_across_multiple_writes() {
+    let mut buffer = CircularBuffer::<_, 3>::new();
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert!(buffer.write('3').is_ok());
+    assert_eq!(Ok('2'), buffer.read());
+    assert_eq!(Ok('3'), buffer.read());
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn overwrite_replaces_the_oldest_item_remaining_in_buffer_following_a_read() {
+    let mut buffer = CircularBuffer::<_, 3>::new();
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    assert!(buffer.write('3').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert!(buffer.write('4').is_ok());
+    buffer.overwrite('5');
+    assert_eq!(Ok('3'), buffer.read());
+    assert_eq!(Ok('4'), buffer.read());
+    assert_eq!(Ok('5'), buffer.read());
+}
+
+#[test]
+fn clear_actually_frees_up_its_elements() {
+    let mut buffer = CircularBuffer::<_, 1>::new();
+    let element = Rc::new(());
+    assert!(buffer.write(Rc::clone(&element)).is_ok());
+    assert_eq!(Rc::strong_count(&element), 2);
+    buffer.clear();
+    assert_eq!(Rc::strong_count(&element), 1);
+    assert!(buffer.write(Rc::clone(&element)).is_ok());
+}
+
+#[test]
+fn dropping_the_buffer_drops_the_remaining_elements() {
+    let element = Rc::new(());
+    let mut buffer = CircularBuffer::<_, 3>::default();
+    for _ in 0..5 {
+        buffer.overwrite(Rc::clone(&element));
+    }
+    assert_eq!(Rc::strong_count(&element), 4);
+
+    drop(buffer);
+    assert_eq!(Rc::strong_count(&element), 1);
+}
+
+#[test]
+fn zero_capacity_buffer_holds_nothing() {
+    let mut buffer = CircularBuffer::<_, 0>::new();
+    assert_eq!(Err(Error::FullBuffer), buffer.write('1'));
+    buffer.overwrite('2');
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+    assert_eq!(buffer.capacity(), 0);
+}
+
+#[test]
+fn elements_are_stored_inline() {
+    assert!(core::mem::size_of::<CircularBuffer<u32, 8>>() >= 8 * core::mem::size_of::<u32>());
+}
@@ -0,0 +1,261 @@
+use crate::ring::Ring;
+use crate::Error;
+use alloc::boxed::Box;
+use core::mem::MaybeUninit;
+#[cfg(test)]
+use std::rc::Rc;
+
+/// A first-in, first-out queue of at most `capacity` elements.
+///
+/// The slots are allocated once, by `new`, and no other operation
+/// allocates. For a buffer whose capacity is known at compile time and
+/// that does not allocate at all, see `array::CircularBuffer`.
+pub struct CircularBuffer<T> {
+    ring: Ring<T, Box<[MaybeUninit<T>]>>,
+}
+
+impl<T> CircularBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        CircularBuffer {
+            ring: Ring::new(Box::new_uninit_slice(capacity)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.ring.is_full()
+    }
+
+    pub fn write(&mut self, element: T) -> Result<(), Error> {
+        self.ring.write(element)
+    }
+
+    pub fn read(&mut self) -> Result<T, Error> {
+        self.ring.read()
+    }
+
+    pub fn clear(&mut self) {
+        self.ring.clear();
+    }
+
+    /// Write `element`, making room by dropping the oldest element if the
+    /// buffer is full. With a capacity of 0, `element` is dropped.
+    pub fn overwrite(&mut self, element: T) {
+        self.ring.overwrite(element);
+    }
+}
+
+#[test]
+fn error_on_read_empty_buffer() {
+    let mut buffer = CircularBuffer::<char>::new(1);
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn can_read_item_just_written() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+}
+
+#[test]
+fn each_item_may_only_be_read_once() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn items_are_read_in_the_order_they_are_written() {
+    let mut buffer = CircularBuffer::new(2);
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert_eq!(Ok('2'), buffer.read());
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn full_buffer_cant_be_written_to() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Err(Error::FullBuffer), buffer.write('2'));
+}
+
+#[test]
+fn read_frees_up_capacity_for_another_write() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert!(buffer.write('2').is_ok());
+    assert_eq!(Ok('2'), buffer.read());
+}
+
+#[test]
+fn read_position_is_maintained_even_across_multiple_writes() {
+    let mut buffer = CircularBuffer::new(3);
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert!(buffer.write('3').is_ok());
+    assert_eq!(Ok('2'), buffer.read());
+    assert_eq!(Ok('3'), buffer.read());
+}
+
+#[test]
+fn items_cleared_out_of_buffer_cant_be_read() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    buffer.clear();
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn clear_frees_up_capacity_for_another_write() {
+    let mut buffer = CircularBuffer::new(1);
+    assert!(buffer.write('1').is_ok());
+    buffer.clear();
+    assert!(buffer.write('2').is_ok());
+    assert_eq!(Ok('2'), buffer.read());
+}
+
+#[test]
+fn clear_does_nothing_on_empty_buffer() {
+    let mut buffer = CircularBuffer::new(1);
+    buffer.clear();
+    assert!(buffer.write('1').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+}
+
+#[test]
+fn clear_actually_frees_up_its_elements() {
+    let mut buffer = CircularBuffer::new(1);
+    let element = Rc::new(());
+    assert!(buffer.write(Rc::clone(&element)).is_ok());
+    assert_eq!(Rc::strong_count(&element), 2);
+    buffer.clear();
+    assert_eq!(Rc::strong_count(&element), 1);
+}
+
+#[test]
+fn overwrite_acts_like_write_on_non_full_buffer() {
+    let mut buffer = CircularBuffer::new(2);
+    assert!(buffer.write('1').is_ok());
+    buffer.overwrite('2');
+    assert_eq!(Ok('1'), buffer.read());
+    assert_eq!(Ok('2'), buffer.read());
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn overwrite_replaces_the_oldest_item_on_full_buffer() {
+    let mut buffer = CircularBuffer::new(2);
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    buffer.overwrite('A');
+    assert_eq!(Ok('2'), buffer.read());
+    assert_eq!(Ok('A'), buffer.read());
+}
+
+#[test]
+fn overwrite_replaces_the_oldest_item_remaining_in_buffer_following_a_read() {
+    let mut buffer = CircularBuffer::new(3);
+    assert!(buffer.write('1').is_ok());
+    assert!(buffer.write('2').is_ok());
+    assert!(buffer.write('3').is_ok());
+    assert_eq!(Ok('1'), buffer.read());
+    assert!(buffer.write('4').is_ok());
+    buffer.overwrite('5');
+    assert_eq!(Ok('3'), buffer.read());
+    assert_eq!(Ok('4'), buffer.read());
+    assert_eq!(Ok('5'), buffer.read());
+}
+
+#[test]
+fn integer_buffer() {
+    let mut buffer = CircularBuffer::new(2);
+    assert!(buffer.write(1).is_ok());
+    assert!(buffer.write(2).is_ok());
+    assert_eq!(Ok(1), buffer.read());
+    assert!(buffer.write(-1).is_ok());
+    assert_eq!(Ok(2), buffer.read());
+    assert_eq!(Ok(-1), buffer.read());
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+}
+
+#[test]
+fn string_buffer() {
+    let mut buffer = CircularBuffer::new(2);
+    buffer.write("".to_string()).unwrap();
+    buffer.write("Testing".to_string()).unwrap();
+    assert_eq!(0, buffer.read().unwrap().len());
+    assert_eq!(Ok("Testing".to_string()), buffer.read());
+}
+
+#[test]
+fn zero_capacity_buffer_holds_nothing() {
+    let mut buffer = CircularBuffer::new(0);
+    assert_eq!(Err(Error::FullBuffer), buffer.write('1'));
+    buffer.overwrite('2');
+    assert_eq!(Err(Error::EmptyBuffer), buffer.read());
+    assert!(buffer.is_empty() && buffer.is_full());
+}
+
+#[test]
+fn overwrite_drops_the_replaced_element() {
+    let mut buffer = CircularBuffer::new(2);
+    let first = Rc::new(1);
+    let second = Rc::new(2);
+    buffer.write(Rc::clone(&first)).unwrap();
+    buffer.write(Rc::clone(&second)).unwrap();
+
+    buffer.overwrite(Rc::new(3));
+    assert_eq!(Rc::strong_count(&first), 1);
+    assert_eq!(Rc::strong_count(&second), 2);
+    assert_eq!(buffer.len(), 2);
+}
+
+#[test]
+fn dropping_the_buffer_drops_the_remaining_elements() {
+    let element = Rc::new(());
+    let mut buffer = CircularBuffer::new(3);
+    for _ in 0..5 {
+        buffer.overwrite(Rc::clone(&element));
+    }
+    drop(buffer.read());
+    assert_eq!(Rc::strong_count(&element), 3);
+
+    drop(buffer);
+    assert_eq!(Rc::strong_count(&element), 1);
+}
+
+#[test]
+fn elements_wrap_around_the_end_of_the_slots() {
+    let mut buffer = CircularBuffer::new(3);
+    for i in 0..100 {
+        assert!(buffer.write(i).is_ok());
+        assert!(buffer.write(i + 1000).is_ok());
+        assert_eq!(Ok(i), buffer.read());
+        assert_eq!(Ok(i + 1000), buffer.read());
+        assert_eq!(buffer.capacity(), 3);
+    }
+
+    buffer.write(1).unwrap();
+    buffer.write(2).unwrap();
+    buffer.clear();
+    buffer.write(3).unwrap();
+    assert_eq!(buffer.len(), 1);
+    assert_eq!(Ok(3), buffer.read());
+}
@@ -0,0 +1,101 @@
+use crate::Error;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+/// The queue behind both circular buffers, over slots that are either
+/// allocated once or stored inline.
+///
+/// The `len` elements start at `head` and wrap around the end of the
+/// slots, so every operation only moves indices.
+pub(crate) struct Ring<T, S: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> {
+    slots: S,
+    head: usize,
+    len: usize,
+    /// The ring owns and drops its elements.
+    elements: PhantomData<T>,
+}
+
+impl<T, S: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> Ring<T, S> {
+    /// An empty ring, whose slots must all be uninitialized.
+    pub(crate) const fn new(slots: S) -> Self {
+        Ring {
+            slots,
+            head: 0,
+            len: 0,
+            elements: PhantomData,
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.slots.as_ref().len()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    pub(crate) fn write(&mut self, element: T) -> Result<(), Error> {
+        if self.is_full() {
+            return Err(Error::FullBuffer);
+        }
+
+        let tail = self.slot(self.len);
+        self.slots.as_mut()[tail].write(element);
+        self.len += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn read(&mut self) -> Result<T, Error> {
+        if self.len == 0 {
+            return Err(Error::EmptyBuffer);
+        }
+
+        // SAFETY: the slot at `head` holds the oldest of the `len`
+        // initialized elements. Moving `head` past it right after makes
+        // sure it is neither read nor dropped again.
+        let element = unsafe { self.slots.as_ref()[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.len -= 1;
+
+        Ok(element)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        while self.read().is_ok() {}
+        self.head = 0;
+    }
+
+    pub(crate) fn overwrite(&mut self, element: T) {
+        // Drop the oldest element only once the ring is consistent again,
+        // in case its `drop` panics.
+        let oldest = if self.is_full() {
+            self.read().ok()
+        } else {
+            None
+        };
+        let _ = self.write(element);
+        drop(oldest);
+    }
+
+    /// Index of the slot `offset` places after `head`, where `offset` is
+    /// at most the capacity.
+    fn slot(&self, offset: usize) -> usize {
+        let index = self.head + offset;
+        if index >= self.capacity() {
+            index - self.capacity()
+        } else {
+            index
+        }
+    }
+}
+
+impl<T, S: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> Drop for Ring<T, S> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
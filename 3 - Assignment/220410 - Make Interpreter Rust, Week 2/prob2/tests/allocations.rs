@@ -0,0 +1,85 @@
+//! After `new`, a buffer never allocates or frees memory, whatever mix of
+//! operations it runs, and the inline buffer never allocates at all.
+
+use prob2::array;
+#[cfg(feature = "alloc")]
+use prob2::CircularBuffer;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    /// Allocations made by the current thread, so that other tests running
+    /// at the same time do not count.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn operations_do_not_allocate() {
+    let mut buffer = CircularBuffer::new(4);
+    let before = allocations();
+
+    for i in 0..1000u64 {
+        match i % 7 {
+            0..=2 => {
+                let _ = buffer.write(i);
+            }
+            3..=4 => {
+                let _ = buffer.read();
+            }
+            5 => buffer.overwrite(i),
+            _ if i % 70 == 6 => buffer.clear(),
+            _ => {}
+        }
+    }
+
+    assert_eq!(allocations(), before);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn new_allocates_once() {
+    let before = allocations();
+    let buffer = CircularBuffer::<u64>::new(16);
+    assert_eq!(allocations(), before + 1);
+
+    drop(buffer);
+    assert_eq!(allocations(), before + 2);
+}
+
+#[test]
+fn array_buffer_never_allocates() {
+    let before = allocations();
+    let mut buffer = array::CircularBuffer::<u64, 4>::new();
+
+    for i in 0..100 {
+        buffer.overwrite(i);
+        let _ = buffer.write(i);
+        let _ = buffer.read();
+    }
+    buffer.clear();
+    drop(buffer);
+
+    assert_eq!(allocations(), before);
+}
@@ -0,0 +1,382 @@
+use crate::token::{lookup_ident, Position, Token, TokenKind};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Turns Monkey source into tokens, one `next_token` call at a time.
+///
+/// Unknown characters become `Illegal` tokens instead of errors, so that
+/// the parser can report them together with its own errors.
+pub struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Position of the next character.
+    position: Position,
+    finished: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            chars: input.char_indices().peekable(),
+            position: Position::default(),
+            finished: false,
+        }
+    }
+
+    /// The next token, or `Eof` at the end of the input and every call
+    /// after that.
+    pub fn next_token(&mut self) -> Token {
+        use TokenKind::*;
+
+        self.skip_whitespace();
+        let position = self.position;
+        let Some(c) = self.bump() else {
+            return Token::new(Eof, "", position);
+        };
+        let start = self.offset() - c.len_utf8();
+
+        let kind = match c {
+            '=' if self.eat('=') => Eq,
+            '=' => Assign,
+            '!' if self.eat('=') => NotEq,
+            '!' => Bang,
+            '+' => Plus,
+            '-' => Minus,
+            '*' => Asterisk,
+            '/' => Slash,
+            '<' => Lt,
+            '>' => Gt,
+            ',' => Comma,
+            ';' => Semicolon,
+            ':' => Colon,
+            '(' => LParen,
+            ')' => RParen,
+            '{' => LBrace,
+            '}' => RBrace,
+            '[' => LBracket,
+            ']' => RBracket,
+            '"' => return self.read_string(start, position),
+            c if is_identifier_start(c) => {
+                self.eat_while(is_identifier_continue);
+                lookup_ident(&self.input[start..self.offset()])
+            }
+            c if c.is_ascii_digit() => {
+                self.eat_while(|c| c.is_ascii_digit());
+                Int
+            }
+            _ => Illegal,
+        };
+
+        Token::new(kind, &self.input[start..self.offset()], position)
+    }
+
+    /// Read the rest of a string whose opening quote starts at `start`.
+    fn read_string(&mut self, start: usize, position: Position) -> Token {
+        self.eat_while(|c| c != '"');
+        let end = self.offset();
+
+        if self.eat('"') {
+            Token::new(TokenKind::String, &self.input[start + 1..end], position)
+        } else {
+            Token::new(TokenKind::Illegal, &self.input[start..end], position)
+        }
+    }
+
+    /// Byte offset of the next character.
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.input.len(), |&(offset, _)| offset)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let matches = self.chars.peek().is_some_and(|&(_, c)| c == expected);
+        if matches {
+            self.bump();
+        }
+
+        matches
+    }
+
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) {
+        while self.chars.peek().is_some_and(|&(_, c)| predicate(c)) {
+            self.bump();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.eat_while(char::is_whitespace);
+    }
+}
+
+/// Every token up to, but not including, `Eof`.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = token.kind == TokenKind::Eof;
+        (!self.finished).then_some(token)
+    }
+}
+
+/// Identifiers follow the same rules as RPN calculator variables: a letter
+/// or `_`, then letters, digits or `_`.
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+fn kinds_and_literals(input: &str) -> Vec<(TokenKind, String)> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token();
+        tokens.push((token.kind, token.literal));
+        if token.kind == TokenKind::Eof {
+            return tokens;
+        }
+    }
+}
+
+#[cfg(test)]
+fn expected(tokens: &[(TokenKind, &str)]) -> Vec<(TokenKind, String)> {
+    tokens
+        .iter()
+        .map(|&(kind, literal)| (kind, literal.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_next_token_single_characters() {
+    use TokenKind::*;
+
+    assert_eq!(
+        kinds_and_literals("=+(){},;"),
+        expected(&[
+            (Assign, "="),
+            (Plus, "+"),
+            (LParen, "("),
+            (RParen, ")"),
+            (LBrace, "{"),
+            (RBrace, "}"),
+            (Comma, ","),
+            (Semicolon, ";"),
+            (Eof, ""),
+        ])
+    );
+}
+
+#[test]
+fn test_next_token() {
+    use TokenKind::*;
+
+    let input = r#"let five = 5;
+let ten = 10;
+
+let add = fn(x, y) {
+  x + y;
+};
+
+let result = add(five, ten);
+!-/*5;
+5 < 10 > 5;
+
+if (5 < 10) {
+	return true;
+} else {
+	return false;
+}
+
+10 == 10;
+10 != 9;
+"foobar"
+"foo bar"
+[1, 2];
+{"foo": "bar"}
+"#;
+
+    assert_eq!(
+        kinds_and_literals(input),
+        expected(&[
+            (Let, "let"),
+            (Ident, "five"),
+            (Assign, "="),
+            (Int, "5"),
+            (Semicolon, ";"),
+            (Let, "let"),
+            (Ident, "ten"),
+            (Assign, "="),
+            (Int, "10"),
+            (Semicolon, ";"),
+            (Let, "let"),
+            (Ident, "add"),
+            (Assign, "="),
+            (Function, "fn"),
+            (LParen, "("),
+            (Ident, "x"),
+            (Comma, ","),
+            (Ident, "y"),
+            (RParen, ")"),
+            (LBrace, "{"),
+            (Ident, "x"),
+            (Plus, "+"),
+            (Ident, "y"),
+            (Semicolon, ";"),
+            (RBrace, "}"),
+            (Semicolon, ";"),
+            (Let, "let"),
+            (Ident, "result"),
+            (Assign, "="),
+            (Ident, "add"),
+            (LParen, "("),
+            (Ident, "five"),
+            (Comma, ","),
+            (Ident, "ten"),
+            (RParen, ")"),
+            (Semicolon, ";"),
+            (Bang, "!"),
+            (Minus, "-"),
+            (Slash, "/"),
+            (Asterisk, "*"),
+            (Int, "5"),
+            (Semicolon, ";"),
+            (Int, "5"),
+            (Lt, "<"),
+            (Int, "10"),
+            (Gt, ">"),
+            (Int, "5"),
+            (Semicolon, ";"),
+            (If, "if"),
+            (LParen, "("),
+            (Int, "5"),
+            (Lt, "<"),
+            (Int, "10"),
+            (RParen, ")"),
+            (LBrace, "{"),
+            (Return, "return"),
+            (True, "true"),
+            (Semicolon, ";"),
+            (RBrace, "}"),
+            (Else, "else"),
+            (LBrace, "{"),
+            (Return, "return"),
+            (False, "false"),
+            (Semicolon, ";"),
+            (RBrace, "}"),
+            (Int, "10"),
+            (Eq, "=="),
+            (Int, "10"),
+            (Semicolon, ";"),
+            (Int, "10"),
+            (NotEq, "!="),
+            (Int, "9"),
+            (Semicolon, ";"),
+            (String, "foobar"),
+            (String, "foo bar"),
+            (LBracket, "["),
+            (Int, "1"),
+            (Comma, ","),
+            (Int, "2"),
+            (RBracket, "]"),
+            (Semicolon, ";"),
+            (LBrace, "{"),
+            (String, "foo"),
+            (Colon, ":"),
+            (String, "bar"),
+            (RBrace, "}"),
+            (Eof, ""),
+        ])
+    );
+}
+
+#[test]
+fn test_identifiers_take_digits_and_underscores() {
+    use TokenKind::*;
+
+    assert_eq!(
+        kinds_and_literals("_x1 x_2 letter 3x"),
+        expected(&[
+            (Ident, "_x1"),
+            (Ident, "x_2"),
+            (Ident, "letter"),
+            (Int, "3"),
+            (Ident, "x"),
+            (Eof, ""),
+        ])
+    );
+}
+
+#[test]
+fn test_illegal_characters_and_unterminated_strings() {
+    use TokenKind::*;
+
+    assert_eq!(
+        kinds_and_literals("1 @ é \"open"),
+        expected(&[
+            (Int, "1"),
+            (Illegal, "@"),
+            (Illegal, "é"),
+            (Illegal, "\"open"),
+            (Eof, ""),
+        ])
+    );
+}
+
+#[test]
+fn test_positions() {
+    let input = "let x = 5;\n  \"héllo\" != x\n";
+    let positions: Vec<_> = Lexer::new(input)
+        .map(|token| (token.literal, token.position.to_string()))
+        .collect();
+
+    assert_eq!(
+        positions,
+        [
+            ("let", "1:1"),
+            ("x", "1:5"),
+            ("=", "1:7"),
+            ("5", "1:9"),
+            (";", "1:10"),
+            ("héllo", "2:3"),
+            ("!=", "2:11"),
+            ("x", "2:14"),
+        ]
+        .map(|(literal, position)| (literal.to_string(), position.to_string()))
+    );
+
+    let mut lexer = Lexer::new(input);
+    lexer.by_ref().for_each(drop);
+    assert_eq!(
+        lexer.next_token(),
+        Token::new(TokenKind::Eof, "", Position::new(3, 1))
+    );
+}
+
+#[test]
+fn test_iterator_stops_before_eof() {
+    assert_eq!(Lexer::new("").count(), 0);
+    assert_eq!(Lexer::new("  \n\t").count(), 0);
+    assert_eq!(Lexer::new("a + b").count(), 3);
+}
@@ -0,0 +1,5 @@
+mod lexer;
+mod token;
+
+pub use lexer::Lexer;
+pub use token::{lookup_ident, Position, Token, TokenKind};
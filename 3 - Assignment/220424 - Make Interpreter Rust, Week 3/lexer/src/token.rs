@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// Where a token starts in the source. Both fields count from 1, and a
+/// column counts characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Self {
+        Position { line, column }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new(1, 1)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A character the language does not use, or a string that never ends.
+    Illegal,
+    Eof,
+
+    Ident,
+    Int,
+    String,
+
+    Assign,
+    Plus,
+    Minus,
+    Bang,
+    Asterisk,
+    Slash,
+    Lt,
+    Gt,
+    Eq,
+    NotEq,
+
+    Comma,
+    Semicolon,
+    Colon,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+
+    Function,
+    Let,
+    True,
+    False,
+    If,
+    Else,
+    Return,
+}
+
+/// Spelling of every keyword.
+const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("fn", TokenKind::Function),
+    ("let", TokenKind::Let),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("if", TokenKind::If),
+    ("else", TokenKind::Else),
+    ("return", TokenKind::Return),
+];
+
+/// The keyword spelled `ident`, or `Ident` if it is not one.
+pub fn lookup_ident(ident: &str) -> TokenKind {
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == ident)
+        .map_or(TokenKind::Ident, |&(_, kind)| kind)
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TokenKind::*;
+
+        let text = match self {
+            Illegal => "ILLEGAL",
+            Eof => "EOF",
+            Ident => "IDENT",
+            Int => "INT",
+            String => "STRING",
+            Assign => "=",
+            Plus => "+",
+            Minus => "-",
+            Bang => "!",
+            Asterisk => "*",
+            Slash => "/",
+            Lt => "<",
+            Gt => ">",
+            Eq => "==",
+            NotEq => "!=",
+            Comma => ",",
+            Semicolon => ";",
+            Colon => ":",
+            LParen => "(",
+            RParen => ")",
+            LBrace => "{",
+            RBrace => "}",
+            LBracket => "[",
+            RBracket => "]",
+            Function => "fn",
+            Let => "let",
+            True => "true",
+            False => "false",
+            If => "if",
+            Else => "else",
+            Return => "return",
+        };
+        f.write_str(text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The text of the token, without the quotes for a string.
+    pub literal: String,
+    pub position: Position,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, literal: impl Into<String>, position: Position) -> Self {
+        Token {
+            kind,
+            literal: literal.into(),
+            position,
+        }
+    }
+}
+
+#[test]
+fn test_lookup_ident() {
+    assert_eq!(lookup_ident("fn"), TokenKind::Function);
+    assert_eq!(lookup_ident("let"), TokenKind::Let);
+    assert_eq!(lookup_ident("return"), TokenKind::Return);
+    assert_eq!(lookup_ident("lets"), TokenKind::Ident);
+    assert_eq!(lookup_ident("Let"), TokenKind::Ident);
+}
+
+#[test]
+fn test_token_kind_display() {
+    assert_eq!(TokenKind::Ident.to_string(), "IDENT");
+    assert_eq!(TokenKind::NotEq.to_string(), "!=");
+    assert_eq!(TokenKind::Function.to_string(), "fn");
+    assert_eq!(Position::new(3, 14).to_string(), "3:14");
+}
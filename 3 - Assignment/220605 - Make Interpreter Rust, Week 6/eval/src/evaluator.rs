@@ -0,0 +1,506 @@
+use crate::ast::{Block, Expression, InfixOperator, PrefixOperator, Program, Statement};
+use crate::environment::Environment;
+use crate::object::{Function, Object};
+use prob2::ArithmeticMode::Checked;
+use prob2::{Division, EvalError, Numeric};
+use std::rc::Rc;
+
+/// Evaluate `program`, adding its `let` bindings to `environment`.
+/// Returns the value of the last statement, or the first error.
+///
+/// Integer arithmetic is the RPN calculator's: `i32` values, with
+/// division by zero and overflow reported as errors.
+pub fn eval(program: &Program, environment: &Environment) -> Object {
+    let mut result = Object::Null;
+
+    for statement in &program.statements {
+        result = eval_statement(statement, environment);
+        match result {
+            Object::ReturnValue(value) => return *value,
+            Object::Error(_) => return result,
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Like `eval`, but a `return` stays wrapped so that it also leaves the
+/// blocks around this one.
+fn eval_block(block: &Block, environment: &Environment) -> Object {
+    let mut result = Object::Null;
+
+    for statement in &block.statements {
+        result = eval_statement(statement, environment);
+        if matches!(result, Object::ReturnValue(_) | Object::Error(_)) {
+            return result;
+        }
+    }
+
+    result
+}
+
+fn eval_statement(statement: &Statement, environment: &Environment) -> Object {
+    match statement {
+        Statement::Let { name, value } => {
+            let value = eval_expression(value, environment);
+            if value.is_error() {
+                return value;
+            }
+            environment.set(name, value);
+            Object::Null
+        }
+        Statement::Return(value) => match eval_expression(value, environment) {
+            error @ Object::Error(_) => error,
+            value => Object::ReturnValue(Box::new(value)),
+        },
+        Statement::Expression(expression) => eval_expression(expression, environment),
+    }
+}
+
+/// Return early from the enclosing function if `object` is an error.
+macro_rules! propagate {
+    ($object:expr) => {
+        match $object {
+            error @ Object::Error(_) => return error,
+            value => value,
+        }
+    };
+}
+
+fn eval_expression(expression: &Expression, environment: &Environment) -> Object {
+    match expression {
+        Expression::Identifier(name) => environment
+            .get(name)
+            .unwrap_or_else(|| error(format!("identifier not found: {}", name))),
+        Expression::Integer(n) => Object::Integer(*n),
+        Expression::Boolean(b) => Object::Boolean(*b),
+        Expression::Prefix(operator, operand) => {
+            let operand = propagate!(eval_expression(operand, environment));
+            eval_prefix(*operator, operand)
+        }
+        Expression::Infix(operator, lhs, rhs) => {
+            let lhs = propagate!(eval_expression(lhs, environment));
+            let rhs = propagate!(eval_expression(rhs, environment));
+            eval_infix(*operator, lhs, rhs)
+        }
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let condition = propagate!(eval_expression(condition, environment));
+            if condition.is_truthy() {
+                eval_block(consequence, environment)
+            } else if let Some(alternative) = alternative {
+                eval_block(alternative, environment)
+            } else {
+                Object::Null
+            }
+        }
+        Expression::Function { parameters, body } => Object::Function(Rc::new(Function {
+            parameters: parameters.clone(),
+            body: body.clone(),
+            environment: environment.clone(),
+        })),
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            let function = propagate!(eval_expression(function, environment));
+            let mut values = vec![];
+            for argument in arguments {
+                values.push(propagate!(eval_expression(argument, environment)));
+            }
+            apply(function, values)
+        }
+    }
+}
+
+fn eval_prefix(operator: PrefixOperator, operand: Object) -> Object {
+    match (operator, operand) {
+        (PrefixOperator::Not, operand) => Object::Boolean(!operand.is_truthy()),
+        (PrefixOperator::Negate, Object::Integer(n)) => arithmetic(n.negate(Checked)),
+        (operator, operand) => error(format!(
+            "unknown operator: {}{}",
+            operator,
+            operand.type_name()
+        )),
+    }
+}
+
+fn eval_infix(operator: InfixOperator, lhs: Object, rhs: Object) -> Object {
+    use InfixOperator::*;
+
+    match (operator, lhs, rhs) {
+        (Add, Object::Integer(a), Object::Integer(b)) => arithmetic(a.add(b, Checked)),
+        (Subtract, Object::Integer(a), Object::Integer(b)) => arithmetic(a.subtract(b, Checked)),
+        (Multiply, Object::Integer(a), Object::Integer(b)) => arithmetic(a.multiply(b, Checked)),
+        (Divide, Object::Integer(a), Object::Integer(b)) => {
+            arithmetic(a.divide(b, Division::Truncating, Checked))
+        }
+        (Less, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a < b),
+        (Greater, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a > b),
+        (Equal, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a == b),
+        (NotEqual, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a != b),
+        (Equal, Object::Boolean(a), Object::Boolean(b)) => Object::Boolean(a == b),
+        (NotEqual, Object::Boolean(a), Object::Boolean(b)) => Object::Boolean(a != b),
+        (operator, lhs, rhs) if lhs.type_name() != rhs.type_name() => error(format!(
+            "type mismatch: {} {} {}",
+            lhs.type_name(),
+            operator,
+            rhs.type_name()
+        )),
+        (operator, lhs, rhs) => error(format!(
+            "unknown operator: {} {} {}",
+            lhs.type_name(),
+            operator,
+            rhs.type_name()
+        )),
+    }
+}
+
+fn arithmetic(result: Result<i32, EvalError>) -> Object {
+    match result {
+        Ok(n) => Object::Integer(n),
+        Err(e) => error(e.to_string()),
+    }
+}
+
+/// Run the body of `function` in a scope that binds its parameters and
+/// falls back to the environment the function was defined in.
+fn apply(function: Object, arguments: Vec<Object>) -> Object {
+    let Object::Function(function) = function else {
+        return error(format!("not a function: {}", function.type_name()));
+    };
+    if arguments.len() != function.parameters.len() {
+        return error(format!(
+            "wrong number of arguments: expected {}, got {}",
+            function.parameters.len(),
+            arguments.len()
+        ));
+    }
+
+    let environment = Environment::enclosed(&function.environment);
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        environment.set(parameter, argument);
+    }
+
+    match eval_block(&function.body, &environment) {
+        Object::ReturnValue(value) => *value,
+        result => result,
+    }
+}
+
+fn error(message: String) -> Object {
+    Object::Error(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Expression::{Boolean, Integer};
+    use InfixOperator::*;
+
+    fn run(statements: Vec<Statement>) -> Object {
+        eval(&Program::new(statements), &Environment::new())
+    }
+
+    fn expression(expression: Expression) -> Statement {
+        Statement::Expression(expression)
+    }
+
+    fn ident(name: &str) -> Expression {
+        Expression::identifier(name)
+    }
+
+    fn infix(lhs: Expression, operator: InfixOperator, rhs: Expression) -> Expression {
+        Expression::infix(operator, lhs, rhs)
+    }
+
+    fn neg(operand: Expression) -> Expression {
+        Expression::prefix(PrefixOperator::Negate, operand)
+    }
+
+    fn not(operand: Expression) -> Expression {
+        Expression::prefix(PrefixOperator::Not, operand)
+    }
+
+    fn block(statements: Vec<Statement>) -> Block {
+        Block::new(statements)
+    }
+
+    #[test]
+    fn test_eval_integer_expressions() {
+        for (input, expected) in [
+            (Integer(5), 5),
+            (neg(Integer(10)), -10),
+            (infix(Integer(5), Add, Integer(5)), 10),
+            (
+                infix(
+                    infix(Integer(2), Multiply, infix(Integer(5), Add, Integer(10))),
+                    Divide,
+                    Integer(3),
+                ),
+                10,
+            ),
+            (
+                infix(
+                    infix(neg(Integer(50)), Add, Integer(100)),
+                    Subtract,
+                    Integer(50),
+                ),
+                0,
+            ),
+            (infix(Integer(-7), Divide, Integer(2)), -3),
+        ] {
+            assert_eq!(
+                run(vec![expression(input.clone())]),
+                Object::Integer(expected),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_boolean_expressions() {
+        for (input, expected) in [
+            (Boolean(true), true),
+            (infix(Integer(1), Less, Integer(2)), true),
+            (infix(Integer(1), Greater, Integer(2)), false),
+            (infix(Integer(1), Equal, Integer(1)), true),
+            (infix(Integer(1), NotEqual, Integer(2)), true),
+            (infix(Boolean(true), Equal, Boolean(false)), false),
+            (
+                infix(infix(Integer(1), Less, Integer(2)), Equal, Boolean(true)),
+                true,
+            ),
+            (not(Boolean(true)), false),
+            (not(Integer(5)), false),
+            (not(not(Integer(5))), true),
+        ] {
+            assert_eq!(
+                run(vec![expression(input.clone())]),
+                Object::Boolean(expected),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_else_expressions() {
+        let if_else = |condition, alternative: Option<Expression>| {
+            run(vec![expression(Expression::new_if(
+                condition,
+                block(vec![expression(Integer(10))]),
+                alternative.map(|alternative| block(vec![expression(alternative)])),
+            ))])
+        };
+
+        assert_eq!(if_else(Boolean(true), None), Object::Integer(10));
+        assert_eq!(if_else(Boolean(false), None), Object::Null);
+        assert_eq!(if_else(Integer(1), None), Object::Integer(10));
+        assert_eq!(
+            if_else(infix(Integer(1), Greater, Integer(2)), Some(Integer(20))),
+            Object::Integer(20)
+        );
+    }
+
+    #[test]
+    fn test_return_statements() {
+        assert_eq!(
+            run(vec![Statement::Return(Integer(10)), expression(Integer(9)),]),
+            Object::Integer(10)
+        );
+        // if (10 > 1) { if (10 > 1) { return 10; } return 1; }
+        assert_eq!(
+            run(vec![expression(Expression::new_if(
+                infix(Integer(10), Greater, Integer(1)),
+                block(vec![
+                    expression(Expression::new_if(
+                        infix(Integer(10), Greater, Integer(1)),
+                        block(vec![Statement::Return(Integer(10))]),
+                        None,
+                    )),
+                    Statement::Return(Integer(1)),
+                ]),
+                None,
+            ))]),
+            Object::Integer(10)
+        );
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let error = |message: &str| Object::Error(message.to_string());
+
+        assert_eq!(
+            run(vec![
+                expression(infix(Integer(5), Add, Boolean(true))),
+                expression(Integer(5)),
+            ]),
+            error("type mismatch: INTEGER + BOOLEAN")
+        );
+        assert_eq!(
+            run(vec![expression(neg(Boolean(true)))]),
+            error("unknown operator: -BOOLEAN")
+        );
+        assert_eq!(
+            run(vec![expression(infix(Boolean(true), Add, Boolean(false)))]),
+            error("unknown operator: BOOLEAN + BOOLEAN")
+        );
+        assert_eq!(
+            run(vec![expression(Expression::new_if(
+                infix(Integer(10), Greater, Integer(1)),
+                block(vec![
+                    Statement::Return(infix(Boolean(true), Add, Boolean(false))),
+                    expression(Integer(1)),
+                ]),
+                None,
+            ))]),
+            error("unknown operator: BOOLEAN + BOOLEAN")
+        );
+        assert_eq!(
+            run(vec![expression(ident("foobar"))]),
+            error("identifier not found: foobar")
+        );
+        assert_eq!(
+            run(vec![expression(infix(Integer(1), Divide, Integer(0)))]),
+            error("division by zero")
+        );
+        assert_eq!(
+            run(vec![expression(infix(Integer(i32::MAX), Add, Integer(1)))]),
+            error("arithmetic overflow")
+        );
+        assert_eq!(
+            run(vec![expression(Expression::call(Integer(1), vec![]))]),
+            error("not a function: INTEGER")
+        );
+    }
+
+    #[test]
+    fn test_let_statements() {
+        assert_eq!(
+            run(vec![
+                Statement::new_let("a", Integer(5)),
+                Statement::new_let("b", ident("a")),
+                Statement::new_let("c", infix(ident("a"), Add, ident("b"))),
+                expression(infix(ident("c"), Multiply, Integer(2))),
+            ]),
+            Object::Integer(20)
+        );
+        assert_eq!(run(vec![Statement::new_let("a", Integer(5))]), Object::Null);
+    }
+
+    #[test]
+    fn test_function_object() {
+        let result = run(vec![expression(Expression::function(
+            &["x"],
+            block(vec![expression(infix(ident("x"), Add, Integer(2)))]),
+        ))]);
+
+        assert_eq!(result.type_name(), "FUNCTION");
+        assert_eq!(result.to_string(), "fn(x) { (x + 2); }");
+    }
+
+    #[test]
+    fn test_function_application() {
+        let identity = Expression::function(&["x"], block(vec![expression(ident("x"))]));
+        let add = Expression::function(
+            &["x", "y"],
+            block(vec![expression(infix(ident("x"), Add, ident("y")))]),
+        );
+
+        assert_eq!(
+            run(vec![
+                Statement::new_let("identity", identity.clone()),
+                expression(Expression::call(ident("identity"), vec![Integer(5)])),
+            ]),
+            Object::Integer(5)
+        );
+        assert_eq!(
+            run(vec![
+                Statement::new_let("add", add),
+                expression(Expression::call(
+                    ident("add"),
+                    vec![
+                        Integer(5),
+                        Expression::call(ident("add"), vec![Integer(5), Integer(5)]),
+                    ],
+                )),
+            ]),
+            Object::Integer(15)
+        );
+        assert_eq!(
+            run(vec![expression(Expression::call(
+                identity,
+                vec![Integer(5)]
+            ))]),
+            Object::Integer(5)
+        );
+    }
+
+    #[test]
+    fn test_wrong_number_of_arguments() {
+        let identity = Expression::function(&["x"], block(vec![expression(ident("x"))]));
+
+        assert_eq!(
+            run(vec![expression(Expression::call(identity, vec![]))]),
+            Object::Error("wrong number of arguments: expected 1, got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_closures() {
+        // let newAdder = fn(x) { fn(y) { x + y } };
+        // let addTwo = newAdder(2);
+        // addTwo(2);
+        let new_adder = Expression::function(
+            &["x"],
+            block(vec![expression(Expression::function(
+                &["y"],
+                block(vec![expression(infix(ident("x"), Add, ident("y")))]),
+            ))]),
+        );
+
+        assert_eq!(
+            run(vec![
+                Statement::new_let("newAdder", new_adder),
+                Statement::new_let(
+                    "addTwo",
+                    Expression::call(ident("newAdder"), vec![Integer(2)]),
+                ),
+                expression(Expression::call(ident("addTwo"), vec![Integer(2)])),
+            ]),
+            Object::Integer(4)
+        );
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        // let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } };
+        // fact(10);
+        let fact = Expression::function(
+            &["n"],
+            block(vec![expression(Expression::new_if(
+                infix(ident("n"), Less, Integer(2)),
+                block(vec![expression(Integer(1))]),
+                Some(block(vec![expression(infix(
+                    ident("n"),
+                    Multiply,
+                    Expression::call(ident("fact"), vec![infix(ident("n"), Subtract, Integer(1))]),
+                ))])),
+            ))]),
+        );
+
+        assert_eq!(
+            run(vec![
+                Statement::new_let("fact", fact),
+                expression(Expression::call(ident("fact"), vec![Integer(10)])),
+            ]),
+            Object::Integer(3628800)
+        );
+    }
+}
@@ -0,0 +1,9 @@
+mod ast;
+mod environment;
+mod evaluator;
+mod object;
+
+pub use ast::{Block, Expression, InfixOperator, PrefixOperator, Program, Statement};
+pub use environment::Environment;
+pub use evaluator::eval;
+pub use object::{Function, Object};
@@ -0,0 +1,79 @@
+use crate::ast::Block;
+use crate::environment::Environment;
+use std::fmt;
+use std::rc::Rc;
+
+/// The values a Monkey program computes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    Integer(i32),
+    Boolean(bool),
+    Null,
+    /// A value on its way out of the enclosing function, through any
+    /// blocks in between.
+    ReturnValue(Box<Object>),
+    /// Stops evaluation of the whole program.
+    Error(String),
+    Function(Rc<Function>),
+}
+
+/// A function literal together with the environment it was evaluated in.
+#[derive(Debug)]
+pub struct Function {
+    pub parameters: Vec<String>,
+    pub body: Block,
+    pub environment: Environment,
+}
+
+/// Two functions are the same only if they came from the same literal in
+/// the same environment.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.parameters == other.parameters
+            && self.body == other.body
+            && self.environment.ptr_eq(&other.environment)
+    }
+}
+
+impl Object {
+    /// Name of the type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "INTEGER",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::Null => "NULL",
+            Object::ReturnValue(_) => "RETURN_VALUE",
+            Object::Error(_) => "ERROR",
+            Object::Function(_) => "FUNCTION",
+        }
+    }
+
+    /// `false` and `null` are falsy, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Boolean(false) | Object::Null)
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Object::Error(_))
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Integer(n) => write!(f, "{}", n),
+            Object::Boolean(b) => write!(f, "{}", b),
+            Object::Null => f.write_str("null"),
+            Object::ReturnValue(value) => write!(f, "{}", value),
+            Object::Error(message) => write!(f, "ERROR: {}", message),
+            Object::Function(function) => {
+                write!(
+                    f,
+                    "fn({}) {}",
+                    function.parameters.join(", "),
+                    function.body
+                )
+            }
+        }
+    }
+}
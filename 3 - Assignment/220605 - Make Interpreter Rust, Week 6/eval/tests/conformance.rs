@@ -0,0 +1,176 @@
+//! Every RPN calculator program that has a Monkey counterpart gives the
+//! same result when lifted to the AST and run by the tree-walking
+//! evaluator.
+
+use eval::{
+    eval, Environment, Expression, InfixOperator, Object, PrefixOperator, Program, Statement,
+};
+use prob2::{evaluate, tokenize, CalculatorInput, EvalError};
+use proptest::prelude::*;
+
+/// Run the inputs on a stack of expressions. Every `name !` becomes a
+/// `let` and the value left on the stack becomes the last statement.
+///
+/// Returns `None` for inputs Monkey has no operator for, for malformed
+/// programs, and for a store while other operands are still on the
+/// stack, since the `let` would then run before them.
+fn lift(inputs: &[CalculatorInput]) -> Option<Program> {
+    let mut statements = vec![];
+    let mut stack = vec![];
+
+    for input in inputs {
+        let operator = match input {
+            CalculatorInput::Add => InfixOperator::Add,
+            CalculatorInput::Subtract => InfixOperator::Subtract,
+            CalculatorInput::Multiply => InfixOperator::Multiply,
+            CalculatorInput::Divide => InfixOperator::Divide,
+            CalculatorInput::Value(n) => {
+                stack.push(Expression::Integer(*n));
+                continue;
+            }
+            CalculatorInput::Load(name) => {
+                stack.push(Expression::identifier(name));
+                continue;
+            }
+            CalculatorInput::Negate => {
+                let operand = stack.pop()?;
+                stack.push(Expression::prefix(PrefixOperator::Negate, operand));
+                continue;
+            }
+            CalculatorInput::Store(name) if stack.len() == 1 => {
+                statements.push(Statement::new_let(name, stack.pop()?));
+                continue;
+            }
+            _ => return None,
+        };
+        let rhs = stack.pop()?;
+        let lhs = stack.pop()?;
+        stack.push(Expression::infix(operator, lhs, rhs));
+    }
+
+    if stack.len() != 1 {
+        return None;
+    }
+    statements.push(Statement::Expression(stack.pop()?));
+    Some(Program::new(statements))
+}
+
+/// The object Monkey should give for an RPN result.
+fn expected(result: Result<i32, EvalError>) -> Object {
+    match result {
+        Ok(n) => Object::Integer(n),
+        Err(EvalError::UnboundVariable(name)) => {
+            Object::Error(format!("identifier not found: {}", name))
+        }
+        Err(error) => Object::Error(error.to_string()),
+    }
+}
+
+fn check(inputs: &[CalculatorInput]) -> Result<(), TestCaseError> {
+    let program = lift(inputs);
+    prop_assert!(program.is_some(), "{:?} has no Monkey counterpart", inputs);
+    let program = program.unwrap();
+
+    prop_assert_eq!(
+        eval(&program, &Environment::new()),
+        expected(evaluate(inputs)),
+        "{}",
+        program
+    );
+    Ok(())
+}
+
+#[test]
+fn test_hand_written_programs() {
+    for source in [
+        "10",
+        "4 8 + 7 5 - /",
+        "5 neg 3 -",
+        "6 x ! x @ x @ *",
+        "1 x ! 2 x ! x @",
+        "3 a ! 4 b ! a @ b @ *",
+        "1 0 /",
+        "2147483647 1 +",
+        "-2147483648 -1 /",
+        "-2147483648 neg",
+        "1 y @ +",
+        "y @ 1 0 / +",
+        "1 0 / x ! 2",
+    ] {
+        check(&tokenize(source).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn test_programs_without_counterpart() {
+    for source in [
+        "",
+        "2 +",
+        "2 2",
+        "1 2 swap -",
+        "1 2 x ! +",
+        "3 a ! a @ 4 b ! b @ *",
+        "3 4 <",
+        "7 2 %",
+    ] {
+        assert_eq!(lift(&tokenize(source).unwrap()), None, "{}", source);
+    }
+}
+
+fn expression() -> impl Strategy<Value = Vec<CalculatorInput>> {
+    use CalculatorInput::*;
+
+    let leaf = prop_oneof![
+        4 => prop_oneof![
+            Just(0),
+            Just(1),
+            Just(-1),
+            Just(i32::MIN),
+            Just(i32::MAX),
+            -100..100,
+        ]
+        .prop_map(|n| vec![Value(n)]),
+        1 => prop::sample::select(vec!["x", "y"]).prop_map(|name| vec![Load(name.to_string())]),
+    ];
+
+    leaf.prop_recursive(5, 32, 2, |inner| {
+        prop_oneof![
+            1 => inner.clone().prop_map(|mut operand| {
+                operand.push(Negate);
+                operand
+            }),
+            4 => (
+                inner.clone(),
+                inner,
+                prop::sample::select(vec![Add, Subtract, Multiply, Divide]),
+            )
+                .prop_map(|(mut lhs, rhs, operator)| {
+                    lhs.extend(rhs);
+                    lhs.push(operator);
+                    lhs
+                }),
+        ]
+    })
+}
+
+/// A few `expression name !` statements followed by one expression.
+fn program() -> impl Strategy<Value = Vec<CalculatorInput>> {
+    let assignment = (expression(), prop::sample::select(vec!["x", "y"]));
+
+    (prop::collection::vec(assignment, 0..4), expression()).prop_map(|(assignments, result)| {
+        let mut inputs = vec![];
+        for (value, name) in assignments {
+            inputs.extend(value);
+            inputs.push(CalculatorInput::Store(name.to_string()));
+        }
+        inputs.extend(result);
+        inputs
+    })
+}
+
+proptest! {
+    #[test]
+    fn lifted_programs_evaluate_identically(inputs in program()) {
+        check(&inputs)?;
+    }
+}
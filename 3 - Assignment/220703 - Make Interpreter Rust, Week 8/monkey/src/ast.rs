@@ -0,0 +1,185 @@
+use std::fmt;
+
+/// A whole Monkey program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+/// The statements between `{` and `}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Let { name: String, value: Expression },
+    Return(Expression),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixOperator {
+    /// `!`
+    Not,
+    /// `-`
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfixOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Less,
+    Greater,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Identifier(String),
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Expression>),
+    /// Key and value pairs, in source order.
+    Hash(Vec<(Expression, Expression)>),
+    Prefix(PrefixOperator, Box<Expression>),
+    Infix(InfixOperator, Box<Expression>, Box<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    If {
+        condition: Box<Expression>,
+        consequence: Block,
+        alternative: Option<Block>,
+    },
+    Function {
+        parameters: Vec<String>,
+        body: Block,
+    },
+    Call {
+        function: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+}
+
+impl fmt::Display for PrefixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefixOperator::Not => f.write_str("!"),
+            PrefixOperator::Negate => f.write_str("-"),
+        }
+    }
+}
+
+impl fmt::Display for InfixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            InfixOperator::Add => "+",
+            InfixOperator::Subtract => "-",
+            InfixOperator::Multiply => "*",
+            InfixOperator::Divide => "/",
+            InfixOperator::Less => "<",
+            InfixOperator::Greater => ">",
+            InfixOperator::Equal => "==",
+            InfixOperator::NotEqual => "!=",
+        };
+        f.write_str(symbol)
+    }
+}
+
+/// Monkey source, with every prefix, infix and index expression in
+/// parentheses so that the tree can be read back from the text.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Identifier(name) => f.write_str(name),
+            Expression::Integer(n) => write!(f, "{}", n),
+            Expression::Boolean(b) => write!(f, "{}", b),
+            Expression::String(s) => write!(f, "\"{}\"", s),
+            Expression::Array(elements) => {
+                write!(f, "[")?;
+                write_separated(f, elements, ", ")?;
+                write!(f, "]")
+            }
+            Expression::Hash(pairs) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in pairs.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Expression::Prefix(operator, operand) => write!(f, "({}{})", operator, operand),
+            Expression::Infix(operator, lhs, rhs) => write!(f, "({} {} {})", lhs, operator, rhs),
+            Expression::Index(collection, index) => write!(f, "({}[{}])", collection, index),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                write!(f, "if {} {}", condition, consequence)?;
+                match alternative {
+                    Some(alternative) => write!(f, " else {}", alternative),
+                    None => Ok(()),
+                }
+            }
+            Expression::Function { parameters, body } => {
+                write!(f, "fn({}) {}", parameters.join(", "), body)
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                write!(f, "{}(", function)?;
+                write_separated(f, arguments, ", ")?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Let { name, value } => write!(f, "let {} = {};", name, value),
+            Statement::Return(value) => write!(f, "return {};", value),
+            Statement::Expression(expression) => write!(f, "{};", expression),
+        }
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{ ")?;
+        for statement in &self.statements {
+            write!(f, "{} ", statement)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_separated(f, &self.statements, " ")
+    }
+}
+
+fn write_separated(
+    f: &mut fmt::Formatter<'_>,
+    items: &[impl fmt::Display],
+    separator: &str,
+) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
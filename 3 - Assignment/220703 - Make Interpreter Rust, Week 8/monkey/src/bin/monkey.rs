@@ -0,0 +1,81 @@
+use monkey::Interpreter;
+use std::io::{self, BufRead, Write};
+
+/// An interpreter whose bindings are kept from one line to the next.
+struct Repl<W = io::Stdout> {
+    interpreter: Interpreter<W>,
+}
+
+impl<W: Write> Repl<W> {
+    fn new(output: W) -> Self {
+        Repl {
+            interpreter: Interpreter::with_output(output),
+        }
+    }
+
+    /// Handle one line of input and return the text to print.
+    fn handle(&mut self, line: &str) -> String {
+        if line.trim().is_empty() {
+            return String::new();
+        }
+
+        match self.interpreter.run(line) {
+            Ok(value) => value.to_string(),
+            Err(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| format!("parse error: {}", error))
+                    .collect();
+                errors.join("\n")
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut repl = Repl::new(io::stdout());
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    print!(">> ");
+    stdout.flush()?;
+
+    for line in stdin.lock().lines() {
+        println!("{}", repl.handle(&line?));
+        print!(">> ");
+        stdout.flush()?;
+    }
+
+    println!();
+    Ok(())
+}
+
+#[test]
+fn test_bindings_are_kept_between_lines() {
+    let mut repl = Repl::new(io::sink());
+    assert_eq!(repl.handle("let people = [{\"name\": \"Anna\"}];"), "null");
+    assert_eq!(repl.handle("people[0][\"name\"]"), "Anna");
+    assert_eq!(repl.handle("push(people, 1)"), "[{name: Anna}, 1]");
+    assert_eq!(repl.handle(""), "");
+}
+
+#[test]
+fn test_errors() {
+    let mut repl = Repl::new(io::sink());
+    assert_eq!(
+        repl.handle("len(1)"),
+        "ERROR: argument to `len` not supported, got INTEGER"
+    );
+    assert_eq!(
+        repl.handle("let x 1; if (x) {"),
+        "parse error: 1:7: expected next token to be =, got INT instead\n\
+         parse error: 1:18: expected }, got EOF instead"
+    );
+}
+
+#[test]
+fn test_puts_writes_to_the_output() {
+    let mut repl = Repl::new(vec![]);
+    assert_eq!(repl.handle("puts(lower(\"HI\"))"), "null");
+    assert_eq!(repl.interpreter.output(), b"hi\n");
+}
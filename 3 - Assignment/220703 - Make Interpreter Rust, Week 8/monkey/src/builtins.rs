@@ -0,0 +1,238 @@
+use crate::object::{Builtin, Object};
+use std::io::Write;
+
+/// Every built-in function. A program can hide one with a `let` of the
+/// same name.
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "len",
+        function: len,
+    },
+    Builtin {
+        name: "first",
+        function: first,
+    },
+    Builtin {
+        name: "last",
+        function: last,
+    },
+    Builtin {
+        name: "rest",
+        function: rest,
+    },
+    Builtin {
+        name: "push",
+        function: push,
+    },
+    Builtin {
+        name: "puts",
+        function: puts,
+    },
+    Builtin {
+        name: "split",
+        function: split,
+    },
+    Builtin {
+        name: "lower",
+        function: lower,
+    },
+];
+
+pub(crate) fn lookup(name: &str) -> Option<Builtin> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .copied()
+}
+
+/// Number of characters of a string, or elements of an array.
+fn len(arguments: &[Object], _: &mut dyn Write) -> Object {
+    match arguments {
+        [Object::String(s)] => Object::Integer(s.chars().count() as i64),
+        [Object::Array(elements)] => Object::Integer(elements.len() as i64),
+        [argument] => error(format!(
+            "argument to `len` not supported, got {}",
+            argument.type_name()
+        )),
+        _ => wrong_number_of_arguments(arguments, 1),
+    }
+}
+
+fn first(arguments: &[Object], _: &mut dyn Write) -> Object {
+    let elements = match array_argument("first", arguments) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+    elements.first().cloned().unwrap_or(Object::Null)
+}
+
+fn last(arguments: &[Object], _: &mut dyn Write) -> Object {
+    let elements = match array_argument("last", arguments) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+    elements.last().cloned().unwrap_or(Object::Null)
+}
+
+/// Every element but the first, as a new array.
+fn rest(arguments: &[Object], _: &mut dyn Write) -> Object {
+    let elements = match array_argument("rest", arguments) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+    match elements {
+        [] => Object::Null,
+        [_, rest @ ..] => Object::Array(rest.to_vec()),
+    }
+}
+
+/// A new array with one more element. The argument is left as it was.
+fn push(arguments: &[Object], _: &mut dyn Write) -> Object {
+    match arguments {
+        [Object::Array(elements), element] => {
+            let mut elements = elements.clone();
+            elements.push(element.clone());
+            Object::Array(elements)
+        }
+        [array, _] => error(format!(
+            "argument to `push` must be ARRAY, got {}",
+            array.type_name()
+        )),
+        _ => wrong_number_of_arguments(arguments, 2),
+    }
+}
+
+/// Write every argument on a line of its own.
+fn puts(arguments: &[Object], output: &mut dyn Write) -> Object {
+    for argument in arguments {
+        if let Err(e) = writeln!(output, "{}", argument) {
+            return error(format!("could not write output: {}", e));
+        }
+    }
+    Object::Null
+}
+
+/// The words of a string, split the way `word_count` from Assignment 2
+/// splits them: `split("Don't panic, it's 42!")` is
+/// `["Don't", "panic", "it's", "42"]`.
+fn split(arguments: &[Object], _: &mut dyn Write) -> Object {
+    match string_argument("split", arguments) {
+        Ok(s) => Object::Array(
+            prob1::split_words(s)
+                .into_iter()
+                .map(|word| Object::String(word.to_string()))
+                .collect(),
+        ),
+        Err(error) => error,
+    }
+}
+
+/// A string in the case `word_count` counts words in.
+fn lower(arguments: &[Object], _: &mut dyn Write) -> Object {
+    match string_argument("lower", arguments) {
+        Ok(s) => Object::String(prob1::normalize(s)),
+        Err(error) => error,
+    }
+}
+
+fn array_argument<'a>(name: &str, arguments: &'a [Object]) -> Result<&'a [Object], Object> {
+    match arguments {
+        [Object::Array(elements)] => Ok(elements),
+        [argument] => Err(error(format!(
+            "argument to `{}` must be ARRAY, got {}",
+            name,
+            argument.type_name()
+        ))),
+        _ => Err(wrong_number_of_arguments(arguments, 1)),
+    }
+}
+
+fn string_argument<'a>(name: &str, arguments: &'a [Object]) -> Result<&'a str, Object> {
+    match arguments {
+        [Object::String(s)] => Ok(s),
+        [argument] => Err(error(format!(
+            "argument to `{}` must be STRING, got {}",
+            name,
+            argument.type_name()
+        ))),
+        _ => Err(wrong_number_of_arguments(arguments, 1)),
+    }
+}
+
+fn wrong_number_of_arguments(arguments: &[Object], want: usize) -> Object {
+    error(format!(
+        "wrong number of arguments. got={}, want={}",
+        arguments.len(),
+        want
+    ))
+}
+
+fn error(message: String) -> Object {
+    Object::Error(message)
+}
+
+#[cfg(test)]
+fn call(name: &str, arguments: &[Object]) -> Object {
+    let builtin = lookup(name).unwrap();
+    (builtin.function)(arguments, &mut std::io::sink())
+}
+
+#[cfg(test)]
+fn strings(words: &[&str]) -> Object {
+    Object::Array(
+        words
+            .iter()
+            .map(|word| Object::String(word.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_lookup() {
+    assert_eq!(lookup("len").map(|builtin| builtin.name), Some("len"));
+    assert_eq!(lookup("lower").map(|builtin| builtin.name), Some("lower"));
+    assert_eq!(lookup("length"), None);
+}
+
+#[test]
+fn test_split_and_lower_follow_word_count() {
+    assert_eq!(
+        call(
+            "split",
+            &[Object::String("Don't panic, it's 42!".to_string())]
+        ),
+        strings(&["Don't", "panic", "it's", "42"])
+    );
+    assert_eq!(
+        call("split", &[Object::String(" ,, ".to_string())]),
+        strings(&[])
+    );
+    assert_eq!(
+        call("lower", &[Object::String("GO Go go".to_string())]),
+        Object::String("go go go".to_string())
+    );
+    assert_eq!(
+        call("split", &[Object::Integer(1)]),
+        error("argument to `split` must be STRING, got INTEGER".to_string())
+    );
+    assert_eq!(
+        call("lower", &[]),
+        error("wrong number of arguments. got=0, want=1".to_string())
+    );
+}
+
+#[test]
+fn test_puts_writes_each_argument_on_a_line() {
+    let mut output = vec![];
+    let result = (lookup("puts").unwrap().function)(
+        &[
+            Object::String("hello".to_string()),
+            Object::Integer(1),
+            Object::Array(vec![Object::Boolean(true)]),
+        ],
+        &mut output,
+    );
+
+    assert_eq!(result, Object::Null);
+    assert_eq!(String::from_utf8(output).unwrap(), "hello\n1\n[true]\n");
+}
@@ -0,0 +1,82 @@
+use crate::object::Object;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// The bindings visible at some point of a program. Cloning gives another
+/// handle to the same bindings, which is how a function keeps the
+/// environment it was defined in.
+#[derive(Clone, Default)]
+pub struct Environment(Rc<RefCell<Scope>>);
+
+#[derive(Default)]
+struct Scope {
+    bindings: HashMap<String, Object>,
+    outer: Option<Environment>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A new scope whose lookups fall back to `outer`.
+    pub fn enclosed(outer: &Environment) -> Self {
+        Environment(Rc::new(RefCell::new(Scope {
+            bindings: HashMap::new(),
+            outer: Some(outer.clone()),
+        })))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        let scope = self.0.borrow();
+        match scope.bindings.get(name) {
+            Some(value) => Some(value.clone()),
+            None => scope.outer.as_ref()?.get(name),
+        }
+    }
+
+    /// Bind `name` in this scope, hiding any binding of an outer scope.
+    pub fn set(&self, name: &str, value: Object) {
+        self.0.borrow_mut().bindings.insert(name.to_string(), value);
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &Environment) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Only the names, since a function bound in an environment holds that
+/// same environment.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = self.0.borrow();
+        let mut names: Vec<_> = scope.bindings.keys().collect();
+        names.sort();
+
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("outer", &scope.outer)
+            .finish()
+    }
+}
+
+#[test]
+fn test_enclosed_scope_falls_back_to_outer() {
+    let outer = Environment::new();
+    outer.set("x", Object::Integer(1));
+    outer.set("y", Object::Integer(2));
+
+    let inner = Environment::enclosed(&outer);
+    inner.set("x", Object::Integer(10));
+
+    assert_eq!(inner.get("x"), Some(Object::Integer(10)));
+    assert_eq!(inner.get("y"), Some(Object::Integer(2)));
+    assert_eq!(outer.get("x"), Some(Object::Integer(1)));
+    assert_eq!(inner.get("z"), None);
+
+    // Both handles see a binding added later to the outer scope.
+    outer.set("z", Object::Boolean(true));
+    assert_eq!(inner.get("z"), Some(Object::Boolean(true)));
+}
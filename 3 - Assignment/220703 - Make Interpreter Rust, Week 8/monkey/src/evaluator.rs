@@ -0,0 +1,585 @@
+use crate::ast::{Block, Expression, InfixOperator, PrefixOperator, Program, Statement};
+use crate::builtins;
+use crate::environment::Environment;
+use crate::object::{Function, Object};
+use crate::parser::{parse, ParseError};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Parses and evaluates source text one piece at a time, keeping the
+/// bindings of every piece for the next, as the REPL does.
+pub struct Interpreter<W = io::Stdout> {
+    environment: Environment,
+    output: W,
+}
+
+impl Interpreter {
+    /// An interpreter whose `puts` prints to standard output.
+    pub fn new() -> Self {
+        Interpreter::with_output(io::stdout())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl<W: Write> Interpreter<W> {
+    /// An interpreter whose `puts` writes to `output`.
+    pub fn with_output(output: W) -> Self {
+        Interpreter {
+            environment: Environment::new(),
+            output,
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Evaluate `source`, or return its parse errors without running any
+    /// of it.
+    pub fn run(&mut self, source: &str) -> Result<Object, Vec<ParseError>> {
+        let program = parse(source)?;
+        Ok(eval(&program, &self.environment, &mut self.output))
+    }
+}
+
+/// Evaluate `program`, adding its `let` bindings to `environment` and
+/// writing the output of `puts` to `output`. Returns the value of the
+/// last statement, or the first error.
+///
+/// Integers are `i64`, and division by zero and overflow are errors.
+pub fn eval(program: &Program, environment: &Environment, output: &mut dyn Write) -> Object {
+    let mut evaluator = Evaluator { output };
+    let mut result = Object::Null;
+
+    for statement in &program.statements {
+        result = evaluator.eval_statement(statement, environment);
+        match result {
+            Object::ReturnValue(value) => return *value,
+            Object::Error(_) => return result,
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Return early from the enclosing function if `object` is an error.
+macro_rules! propagate {
+    ($object:expr) => {
+        match $object {
+            error @ Object::Error(_) => return error,
+            value => value,
+        }
+    };
+}
+
+/// The state shared by a whole evaluation, which is only where `puts`
+/// writes to.
+struct Evaluator<'a> {
+    output: &'a mut dyn Write,
+}
+
+impl Evaluator<'_> {
+    /// Like `eval`, but a `return` stays wrapped so that it also leaves
+    /// the blocks around this one.
+    fn eval_block(&mut self, block: &Block, environment: &Environment) -> Object {
+        let mut result = Object::Null;
+
+        for statement in &block.statements {
+            result = self.eval_statement(statement, environment);
+            if matches!(result, Object::ReturnValue(_) | Object::Error(_)) {
+                return result;
+            }
+        }
+
+        result
+    }
+
+    fn eval_statement(&mut self, statement: &Statement, environment: &Environment) -> Object {
+        match statement {
+            Statement::Let { name, value } => {
+                let value = propagate!(self.eval_expression(value, environment));
+                environment.set(name, value);
+                Object::Null
+            }
+            Statement::Return(value) => {
+                let value = propagate!(self.eval_expression(value, environment));
+                Object::ReturnValue(Box::new(value))
+            }
+            Statement::Expression(expression) => self.eval_expression(expression, environment),
+        }
+    }
+
+    fn eval_expression(&mut self, expression: &Expression, environment: &Environment) -> Object {
+        match expression {
+            Expression::Identifier(name) => environment
+                .get(name)
+                .or_else(|| builtins::lookup(name).map(Object::Builtin))
+                .unwrap_or_else(|| error(format!("identifier not found: {}", name))),
+            Expression::Integer(n) => Object::Integer(*n),
+            Expression::Boolean(b) => Object::Boolean(*b),
+            Expression::String(s) => Object::String(s.clone()),
+            Expression::Array(elements) => {
+                let mut values = vec![];
+                for element in elements {
+                    values.push(propagate!(self.eval_expression(element, environment)));
+                }
+                Object::Array(values)
+            }
+            Expression::Hash(pairs) => {
+                let mut hash = BTreeMap::new();
+                for (key, value) in pairs {
+                    let key = propagate!(self.eval_expression(key, environment));
+                    let Some(key) = key.hash_key() else {
+                        return unusable_as_hash_key(&key);
+                    };
+                    let value = propagate!(self.eval_expression(value, environment));
+                    hash.insert(key, value);
+                }
+                Object::Hash(hash)
+            }
+            Expression::Prefix(operator, operand) => {
+                let operand = propagate!(self.eval_expression(operand, environment));
+                eval_prefix(*operator, operand)
+            }
+            Expression::Infix(operator, lhs, rhs) => {
+                let lhs = propagate!(self.eval_expression(lhs, environment));
+                let rhs = propagate!(self.eval_expression(rhs, environment));
+                eval_infix(*operator, lhs, rhs)
+            }
+            Expression::Index(collection, index) => {
+                let collection = propagate!(self.eval_expression(collection, environment));
+                let index = propagate!(self.eval_expression(index, environment));
+                eval_index(collection, index)
+            }
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition = propagate!(self.eval_expression(condition, environment));
+                if condition.is_truthy() {
+                    self.eval_block(consequence, environment)
+                } else if let Some(alternative) = alternative {
+                    self.eval_block(alternative, environment)
+                } else {
+                    Object::Null
+                }
+            }
+            Expression::Function { parameters, body } => Object::Function(Rc::new(Function {
+                parameters: parameters.clone(),
+                body: body.clone(),
+                environment: environment.clone(),
+            })),
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let function = propagate!(self.eval_expression(function, environment));
+                let mut values = vec![];
+                for argument in arguments {
+                    values.push(propagate!(self.eval_expression(argument, environment)));
+                }
+                self.apply(function, values)
+            }
+        }
+    }
+
+    /// Run the body of `function` in a scope that binds its parameters and
+    /// falls back to the environment the function was defined in.
+    fn apply(&mut self, function: Object, arguments: Vec<Object>) -> Object {
+        let function = match function {
+            Object::Function(function) => function,
+            Object::Builtin(builtin) => return (builtin.function)(&arguments, self.output),
+            _ => return error(format!("not a function: {}", function.type_name())),
+        };
+        if arguments.len() != function.parameters.len() {
+            return error(format!(
+                "wrong number of arguments: expected {}, got {}",
+                function.parameters.len(),
+                arguments.len()
+            ));
+        }
+
+        let environment = Environment::enclosed(&function.environment);
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            environment.set(parameter, argument);
+        }
+
+        match self.eval_block(&function.body, &environment) {
+            Object::ReturnValue(value) => *value,
+            result => result,
+        }
+    }
+}
+
+fn eval_prefix(operator: PrefixOperator, operand: Object) -> Object {
+    match (operator, operand) {
+        (PrefixOperator::Not, operand) => Object::Boolean(!operand.is_truthy()),
+        (PrefixOperator::Negate, Object::Integer(n)) => arithmetic(n.checked_neg()),
+        (operator, operand) => error(format!(
+            "unknown operator: {}{}",
+            operator,
+            operand.type_name()
+        )),
+    }
+}
+
+fn eval_infix(operator: InfixOperator, lhs: Object, rhs: Object) -> Object {
+    use InfixOperator::*;
+
+    match (operator, lhs, rhs) {
+        (Add, Object::Integer(a), Object::Integer(b)) => arithmetic(a.checked_add(b)),
+        (Subtract, Object::Integer(a), Object::Integer(b)) => arithmetic(a.checked_sub(b)),
+        (Multiply, Object::Integer(a), Object::Integer(b)) => arithmetic(a.checked_mul(b)),
+        (Divide, Object::Integer(_), Object::Integer(0)) => error("division by zero".to_string()),
+        (Divide, Object::Integer(a), Object::Integer(b)) => arithmetic(a.checked_div(b)),
+        (Less, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a < b),
+        (Greater, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a > b),
+        (Equal, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a == b),
+        (NotEqual, Object::Integer(a), Object::Integer(b)) => Object::Boolean(a != b),
+        (Equal, Object::Boolean(a), Object::Boolean(b)) => Object::Boolean(a == b),
+        (NotEqual, Object::Boolean(a), Object::Boolean(b)) => Object::Boolean(a != b),
+        (Add, Object::String(a), Object::String(b)) => Object::String(a + &b),
+        (Equal, Object::String(a), Object::String(b)) => Object::Boolean(a == b),
+        (NotEqual, Object::String(a), Object::String(b)) => Object::Boolean(a != b),
+        (operator, lhs, rhs) if lhs.type_name() != rhs.type_name() => error(format!(
+            "type mismatch: {} {} {}",
+            lhs.type_name(),
+            operator,
+            rhs.type_name()
+        )),
+        (operator, lhs, rhs) => error(format!(
+            "unknown operator: {} {} {}",
+            lhs.type_name(),
+            operator,
+            rhs.type_name()
+        )),
+    }
+}
+
+/// The element at `index`, or `null` if there is none.
+fn eval_index(collection: Object, index: Object) -> Object {
+    match (collection, index) {
+        (Object::Array(elements), Object::Integer(i)) => usize::try_from(i)
+            .ok()
+            .and_then(|i| elements.get(i).cloned())
+            .unwrap_or(Object::Null),
+        (Object::Hash(pairs), key) => match key.hash_key() {
+            Some(key) => pairs.get(&key).cloned().unwrap_or(Object::Null),
+            None => unusable_as_hash_key(&key),
+        },
+        (collection, _) => error(format!(
+            "index operator not supported: {}",
+            collection.type_name()
+        )),
+    }
+}
+
+fn arithmetic(result: Option<i64>) -> Object {
+    match result {
+        Some(n) => Object::Integer(n),
+        None => error("arithmetic overflow".to_string()),
+    }
+}
+
+fn unusable_as_hash_key(key: &Object) -> Object {
+    error(format!("unusable as hash key: {}", key.type_name()))
+}
+
+fn error(message: String) -> Object {
+    Object::Error(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::HashKey;
+
+    fn run(source: &str) -> Object {
+        Interpreter::with_output(io::sink())
+            .run(source)
+            .unwrap_or_else(|errors| panic!("{:?} has errors: {:?}", source, errors))
+    }
+
+    fn check(cases: &[(&str, Object)]) {
+        for (source, expected) in cases {
+            assert_eq!(&run(source), expected, "{}", source);
+        }
+    }
+
+    fn integer(n: i64) -> Object {
+        Object::Integer(n)
+    }
+
+    fn string(s: &str) -> Object {
+        Object::String(s.to_string())
+    }
+
+    fn error(message: &str) -> Object {
+        Object::Error(message.to_string())
+    }
+
+    #[test]
+    fn test_eval_integer_and_boolean_expressions() {
+        check(&[
+            ("5", integer(5)),
+            ("-10", integer(-10)),
+            ("5 + 5 + 5 + 5 - 10", integer(10)),
+            ("2 * (5 + 10) / 3", integer(10)),
+            ("-50 + 100 + -50", integer(0)),
+            ("3 * (3 * 3) + 10", integer(37)),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", integer(50)),
+            ("true", Object::Boolean(true)),
+            ("1 < 2", Object::Boolean(true)),
+            ("1 > 2", Object::Boolean(false)),
+            ("1 != 2", Object::Boolean(true)),
+            ("(1 < 2) == true", Object::Boolean(true)),
+            ("(1 > 2) == true", Object::Boolean(false)),
+            ("!5", Object::Boolean(false)),
+            ("!!5", Object::Boolean(true)),
+            ("if (1 > 2) { 10 }", Object::Null),
+            ("if (1 > 2) { 10 } else { 20 }", integer(20)),
+            (
+                "if (10 > 1) { if (10 > 1) { return 10; } return 1; }",
+                integer(10),
+            ),
+            ("9223372036854775807 + 1", error("arithmetic overflow")),
+            ("-9223372036854775807 - 1", integer(i64::MIN)),
+            (
+                "(-9223372036854775807 - 1) / -1",
+                error("arithmetic overflow"),
+            ),
+            ("1 / 0", error("division by zero")),
+        ]);
+    }
+
+    #[test]
+    fn test_closures() {
+        check(&[
+            ("let identity = fn(x) { x; }; identity(5);", integer(5)),
+            (
+                "let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));",
+                integer(20),
+            ),
+            ("fn(x) { x; }(5)", integer(5)),
+            (
+                "let newAdder = fn(x) { fn(y) { x + y } }; let addTwo = newAdder(2); addTwo(2);",
+                integer(4),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_strings() {
+        check(&[
+            (r#""Hello World!""#, string("Hello World!")),
+            (r#""Hello" + " " + "World!""#, string("Hello World!")),
+            (r#""a" == "a""#, Object::Boolean(true)),
+            (r#""a" != "a""#, Object::Boolean(false)),
+            (
+                r#""Hello" - "World""#,
+                error("unknown operator: STRING - STRING"),
+            ),
+            (r#""1" + 1"#, error("type mismatch: STRING + INTEGER")),
+        ]);
+    }
+
+    #[test]
+    fn test_builtin_functions() {
+        check(&[
+            (r#"len("")"#, integer(0)),
+            (r#"len("four")"#, integer(4)),
+            (r#"len("hello world")"#, integer(11)),
+            (r#"len("héllo")"#, integer(5)),
+            ("len([1, 2, 3])", integer(3)),
+            (
+                "len(1)",
+                error("argument to `len` not supported, got INTEGER"),
+            ),
+            (
+                r#"len("one", "two")"#,
+                error("wrong number of arguments. got=2, want=1"),
+            ),
+            ("first([1, 2, 3])", integer(1)),
+            ("first([])", Object::Null),
+            (
+                "first(1)",
+                error("argument to `first` must be ARRAY, got INTEGER"),
+            ),
+            ("last([1, 2, 3])", integer(3)),
+            ("last([])", Object::Null),
+            (
+                "last(1)",
+                error("argument to `last` must be ARRAY, got INTEGER"),
+            ),
+            (
+                "rest([1, 2, 3])",
+                Object::Array(vec![integer(2), integer(3)]),
+            ),
+            ("rest([1])", Object::Array(vec![])),
+            ("rest([])", Object::Null),
+            ("push([], 1)", Object::Array(vec![integer(1)])),
+            (
+                "push(1, 1)",
+                error("argument to `push` must be ARRAY, got INTEGER"),
+            ),
+            (
+                "let a = [1]; push(a, 2); a",
+                Object::Array(vec![integer(1)]),
+            ),
+            ("puts()", Object::Null),
+            (r#"let lower = fn(s) { s }; lower("A")"#, string("A")),
+        ]);
+    }
+
+    #[test]
+    fn test_split_and_lower() {
+        let map = "let map = fn(arr, f) { \
+            let iter = fn(arr, acc) { \
+                if (len(arr) == 0) { acc } else { iter(rest(arr), push(acc, f(first(arr)))) } \
+            }; \
+            iter(arr, []) \
+        };";
+
+        assert_eq!(
+            run(&format!(
+                r#"{} map(split("Joe can't tell between 'large' and large."), lower)"#,
+                map
+            )),
+            Object::Array(
+                ["joe", "can't", "tell", "between", "large", "and", "large"]
+                    .map(string)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_puts_writes_to_the_output() {
+        let mut interpreter = Interpreter::with_output(vec![]);
+        assert_eq!(
+            interpreter.run(r#"puts("hello", 1 + 2); puts([1, "a"])"#),
+            Ok(Object::Null)
+        );
+        assert_eq!(interpreter.output(), b"hello\n3\n[1, a]\n");
+    }
+
+    #[test]
+    fn test_arrays() {
+        check(&[
+            (
+                "[1, 2 * 2, 3 + 3]",
+                Object::Array(vec![integer(1), integer(4), integer(6)]),
+            ),
+            ("[1, 2, 3][0]", integer(1)),
+            ("[1, 2, 3][1 + 1];", integer(3)),
+            ("let i = 0; [1][i];", integer(1)),
+            (
+                "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+                integer(6),
+            ),
+            (
+                "let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]",
+                integer(2),
+            ),
+            ("[1, 2, 3][3]", Object::Null),
+            ("[1, 2, 3][-1]", Object::Null),
+            ("[[1], [2]][1][0]", integer(2)),
+            ("[1, 2][true]", error("index operator not supported: ARRAY")),
+            ("1[0]", error("index operator not supported: INTEGER")),
+            ("[1, x]", error("identifier not found: x")),
+        ]);
+    }
+
+    #[test]
+    fn test_hashes() {
+        let source = r#"let two = "two";
+        {
+            "one": 10 - 9,
+            two: 1 + 1,
+            "thr" + "ee": 6 / 2,
+            4: 4,
+            true: 5,
+            false: 6
+        }"#;
+
+        assert_eq!(
+            run(source),
+            Object::Hash(BTreeMap::from([
+                (HashKey::String("one".to_string()), integer(1)),
+                (HashKey::String("two".to_string()), integer(2)),
+                (HashKey::String("three".to_string()), integer(3)),
+                (HashKey::Integer(4), integer(4)),
+                (HashKey::Boolean(true), integer(5)),
+                (HashKey::Boolean(false), integer(6)),
+            ]))
+        );
+
+        check(&[
+            (r#"{"foo": 5}["foo"]"#, integer(5)),
+            (r#"{"foo": 5}["bar"]"#, Object::Null),
+            (r#"let key = "foo"; {"foo": 5}[key]"#, integer(5)),
+            (r#"{}["foo"]"#, Object::Null),
+            ("{5: 5}[5]", integer(5)),
+            ("{true: 5}[true]", integer(5)),
+            ("{false: 5}[false]", integer(5)),
+            ("{1: 1, 1: 2}[1]", integer(2)),
+            (
+                r#"{"name": "Monkey"}[fn(x) { x }];"#,
+                error("unusable as hash key: FUNCTION"),
+            ),
+            ("{[1]: 2}", error("unusable as hash key: ARRAY")),
+            ("{{}: 2}", error("unusable as hash key: HASH")),
+            ("{1: x}", error("identifier not found: x")),
+        ]);
+    }
+
+    #[test]
+    fn test_error_handling() {
+        check(&[
+            ("5 + true;", error("type mismatch: INTEGER + BOOLEAN")),
+            ("5 + true; 5;", error("type mismatch: INTEGER + BOOLEAN")),
+            ("-true", error("unknown operator: -BOOLEAN")),
+            (
+                "true + false;",
+                error("unknown operator: BOOLEAN + BOOLEAN"),
+            ),
+            (
+                "if (10 > 1) { if (10 > 1) { return true + false; } return 1; }",
+                error("unknown operator: BOOLEAN + BOOLEAN"),
+            ),
+            ("foobar", error("identifier not found: foobar")),
+            ("let x = 1 / 0; x", error("division by zero")),
+            ("5(1)", error("not a function: INTEGER")),
+            (
+                "fn(x) { x }()",
+                error("wrong number of arguments: expected 1, got 0"),
+            ),
+            ("[1] + [2]", error("unknown operator: ARRAY + ARRAY")),
+        ]);
+    }
+
+    #[test]
+    fn test_bindings_are_kept_between_runs() {
+        let mut interpreter = Interpreter::with_output(io::sink());
+        assert_eq!(interpreter.run("let a = [1, 2];"), Ok(Object::Null));
+        assert_eq!(interpreter.run("push(a, 3)[2]"), Ok(integer(3)));
+        assert!(interpreter.run("let = 1").is_err());
+        assert_eq!(interpreter.run("len(a)"), Ok(integer(2)));
+        assert_eq!(
+            interpreter.environment().get("a"),
+            Some(Object::Array(vec![integer(1), integer(2)]))
+        );
+    }
+}
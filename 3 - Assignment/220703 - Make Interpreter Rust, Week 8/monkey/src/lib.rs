@@ -0,0 +1,12 @@
+mod ast;
+mod builtins;
+mod environment;
+mod evaluator;
+mod object;
+mod parser;
+
+pub use ast::{Block, Expression, InfixOperator, PrefixOperator, Program, Statement};
+pub use environment::Environment;
+pub use evaluator::{eval, Interpreter};
+pub use object::{Builtin, Function, HashKey, Object};
+pub use parser::{parse, ParseError};
@@ -0,0 +1,206 @@
+use crate::ast::Block;
+use crate::environment::Environment;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+/// The values a Monkey program computes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    Null,
+    /// A value on its way out of the enclosing function, through any
+    /// blocks in between.
+    ReturnValue(Box<Object>),
+    /// Stops evaluation of the whole program.
+    Error(String),
+    Function(Rc<Function>),
+    Builtin(Builtin),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
+}
+
+/// A function literal together with the environment it was evaluated in.
+#[derive(Debug)]
+pub struct Function {
+    pub parameters: Vec<String>,
+    pub body: Block,
+    pub environment: Environment,
+}
+
+/// Two functions are the same only if they came from the same literal in
+/// the same environment.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.parameters == other.parameters
+            && self.body == other.body
+            && self.environment.ptr_eq(&other.environment)
+    }
+}
+
+/// A function written in Rust. It gets the evaluated arguments and the
+/// output that `puts` prints to.
+#[derive(Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub function: fn(&[Object], &mut dyn Write) -> Object,
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Builtin").field(&self.name).finish()
+    }
+}
+
+/// The objects that can be used as hash keys. They are compared by
+/// value, so that `{"a": 1}["a"]` finds the pair even though the two
+/// strings are different objects.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl Object {
+    /// Name of the type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "INTEGER",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::String(_) => "STRING",
+            Object::Null => "NULL",
+            Object::ReturnValue(_) => "RETURN_VALUE",
+            Object::Error(_) => "ERROR",
+            Object::Function(_) => "FUNCTION",
+            Object::Builtin(_) => "BUILTIN",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+        }
+    }
+
+    /// `false` and `null` are falsy, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Boolean(false) | Object::Null)
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Object::Error(_))
+    }
+
+    /// The key this object is stored under in a hash, or `None` if it
+    /// cannot be a key.
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(n) => Some(HashKey::Integer(*n)),
+            Object::Boolean(b) => Some(HashKey::Boolean(*b)),
+            Object::String(s) => Some(HashKey::String(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl From<HashKey> for Object {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Integer(n) => Object::Integer(n),
+            HashKey::Boolean(b) => Object::Boolean(b),
+            HashKey::String(s) => Object::String(s),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashKey::Integer(n) => write!(f, "{}", n),
+            HashKey::Boolean(b) => write!(f, "{}", b),
+            HashKey::String(s) => f.write_str(s),
+        }
+    }
+}
+
+/// What the REPL prints, and what `puts` writes. Strings are written
+/// without quotes.
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Integer(n) => write!(f, "{}", n),
+            Object::Boolean(b) => write!(f, "{}", b),
+            Object::String(s) => f.write_str(s),
+            Object::Null => f.write_str("null"),
+            Object::ReturnValue(value) => write!(f, "{}", value),
+            Object::Error(message) => write!(f, "ERROR: {}", message),
+            Object::Function(function) => {
+                write!(
+                    f,
+                    "fn({}) {}",
+                    function.parameters.join(", "),
+                    function.body
+                )
+            }
+            Object::Builtin(_) => f.write_str("builtin function"),
+            Object::Array(elements) => {
+                write!(f, "[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Object::Hash(pairs) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in pairs.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hash_keys_compare_by_value() {
+    let hello1 = Object::String("Hello World".to_string());
+    let hello2 = Object::String("Hello World".to_string());
+    let diff = Object::String("My name is johnny".to_string());
+
+    assert_eq!(hello1.hash_key(), hello2.hash_key());
+    assert_ne!(hello1.hash_key(), diff.hash_key());
+    assert_ne!(
+        Object::Integer(1).hash_key(),
+        Object::Boolean(true).hash_key()
+    );
+    assert_eq!(Object::Null.hash_key(), None);
+    assert_eq!(Object::Array(vec![]).hash_key(), None);
+}
+
+#[test]
+fn test_display() {
+    let array = Object::Array(vec![
+        Object::Integer(1),
+        Object::String("two".to_string()),
+        Object::Array(vec![]),
+    ]);
+    assert_eq!(array.to_string(), "[1, two, []]");
+
+    let hash = Object::Hash(BTreeMap::from([
+        (HashKey::String("b".to_string()), Object::Boolean(true)),
+        (HashKey::Integer(1), Object::Null),
+    ]));
+    assert_eq!(hash.to_string(), "{1: null, b: true}");
+}
@@ -0,0 +1,513 @@
+use crate::ast::{Block, Expression, InfixOperator, PrefixOperator, Program, Statement};
+use lexer::{Lexer, Position, Token, TokenKind};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// How tightly an operator binds, from loosest to tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Lowest,
+    Equals,
+    LessGreater,
+    Sum,
+    Product,
+    Prefix,
+    Call,
+    Index,
+}
+
+fn precedence(kind: TokenKind) -> Precedence {
+    match kind {
+        TokenKind::Eq | TokenKind::NotEq => Precedence::Equals,
+        TokenKind::Lt | TokenKind::Gt => Precedence::LessGreater,
+        TokenKind::Plus | TokenKind::Minus => Precedence::Sum,
+        TokenKind::Asterisk | TokenKind::Slash => Precedence::Product,
+        TokenKind::LParen => Precedence::Call,
+        TokenKind::LBracket => Precedence::Index,
+        _ => Precedence::Lowest,
+    }
+}
+
+fn infix_operator(kind: TokenKind) -> Option<InfixOperator> {
+    match kind {
+        TokenKind::Plus => Some(InfixOperator::Add),
+        TokenKind::Minus => Some(InfixOperator::Subtract),
+        TokenKind::Asterisk => Some(InfixOperator::Multiply),
+        TokenKind::Slash => Some(InfixOperator::Divide),
+        TokenKind::Lt => Some(InfixOperator::Less),
+        TokenKind::Gt => Some(InfixOperator::Greater),
+        TokenKind::Eq => Some(InfixOperator::Equal),
+        TokenKind::NotEq => Some(InfixOperator::NotEqual),
+        _ => None,
+    }
+}
+
+/// Parse Monkey source, or return every error found in it.
+pub fn parse(source: &str) -> Result<Program, Vec<ParseError>> {
+    let mut parser = Parser::new(source);
+    let program = parser.parse_program();
+
+    if parser.errors.is_empty() {
+        Ok(program)
+    } else {
+        Err(parser.errors)
+    }
+}
+
+/// A Pratt parser. Every `parse_` method starts on the first token of what
+/// it parses and stops on its last token. A method that fails records an
+/// error and returns `None`, and the program carries on with the next
+/// token, so that one run reports as many errors as it can.
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    current: Token,
+    peek: Token,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let current = lexer.next_token();
+        let peek = lexer.next_token();
+
+        Parser {
+            lexer,
+            current,
+            peek,
+            errors: vec![],
+        }
+    }
+
+    fn advance(&mut self) {
+        self.current = std::mem::replace(&mut self.peek, self.lexer.next_token());
+    }
+
+    fn peek_is(&self, kind: TokenKind) -> bool {
+        self.peek.kind == kind
+    }
+
+    /// Move on to the next token if it is a `kind`.
+    fn expect_peek(&mut self, kind: TokenKind) -> Option<()> {
+        if self.peek_is(kind) {
+            self.advance();
+            Some(())
+        } else {
+            let message = format!(
+                "expected next token to be {}, got {} instead",
+                kind, self.peek.kind
+            );
+            self.error(self.peek.position, message)
+        }
+    }
+
+    fn error<T>(&mut self, position: Position, message: String) -> Option<T> {
+        self.errors.push(ParseError { message, position });
+        None
+    }
+
+    fn skip_semicolon(&mut self) {
+        if self.peek_is(TokenKind::Semicolon) {
+            self.advance();
+        }
+    }
+
+    fn parse_program(&mut self) -> Program {
+        let mut statements = vec![];
+
+        while self.current.kind != TokenKind::Eof {
+            if let Some(statement) = self.parse_statement() {
+                statements.push(statement);
+            }
+            self.advance();
+        }
+
+        Program { statements }
+    }
+
+    fn parse_statement(&mut self) -> Option<Statement> {
+        let statement = match self.current.kind {
+            TokenKind::Let => {
+                self.expect_peek(TokenKind::Ident)?;
+                let name = self.current.literal.clone();
+                self.expect_peek(TokenKind::Assign)?;
+                self.advance();
+                let value = self.parse_expression(Precedence::Lowest)?;
+                Statement::Let { name, value }
+            }
+            TokenKind::Return => {
+                self.advance();
+                Statement::Return(self.parse_expression(Precedence::Lowest)?)
+            }
+            _ => Statement::Expression(self.parse_expression(Precedence::Lowest)?),
+        };
+        self.skip_semicolon();
+
+        Some(statement)
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Option<Expression> {
+        let mut lhs = self.parse_prefix()?;
+
+        while !self.peek_is(TokenKind::Semicolon) && precedence < self::precedence(self.peek.kind) {
+            self.advance();
+            lhs = self.parse_infix(lhs)?;
+        }
+
+        Some(lhs)
+    }
+
+    fn parse_prefix(&mut self) -> Option<Expression> {
+        let expression = match self.current.kind {
+            TokenKind::Ident => Expression::Identifier(self.current.literal.clone()),
+            TokenKind::Int => match self.current.literal.parse() {
+                Ok(n) => Expression::Integer(n),
+                Err(_) => {
+                    let message = format!("could not parse {} as integer", self.current.literal);
+                    return self.error(self.current.position, message);
+                }
+            },
+            TokenKind::String => Expression::String(self.current.literal.clone()),
+            TokenKind::True => Expression::Boolean(true),
+            TokenKind::False => Expression::Boolean(false),
+            TokenKind::Bang | TokenKind::Minus => {
+                let operator = match self.current.kind {
+                    TokenKind::Bang => PrefixOperator::Not,
+                    _ => PrefixOperator::Negate,
+                };
+                self.advance();
+                let operand = self.parse_expression(Precedence::Prefix)?;
+                Expression::Prefix(operator, Box::new(operand))
+            }
+            TokenKind::LParen => {
+                self.advance();
+                let expression = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenKind::RParen)?;
+                expression
+            }
+            TokenKind::LBracket => Expression::Array(self.parse_list(TokenKind::RBracket)?),
+            TokenKind::LBrace => self.parse_hash()?,
+            TokenKind::If => self.parse_if()?,
+            TokenKind::Function => self.parse_function()?,
+            TokenKind::Illegal => {
+                let message = format!("illegal token `{}`", self.current.literal);
+                return self.error(self.current.position, message);
+            }
+            kind => {
+                let message = format!("no prefix parse function for {} found", kind);
+                return self.error(self.current.position, message);
+            }
+        };
+
+        Some(expression)
+    }
+
+    fn parse_infix(&mut self, lhs: Expression) -> Option<Expression> {
+        let lhs = Box::new(lhs);
+
+        match self.current.kind {
+            TokenKind::LParen => Some(Expression::Call {
+                function: lhs,
+                arguments: self.parse_list(TokenKind::RParen)?,
+            }),
+            TokenKind::LBracket => {
+                self.advance();
+                let index = self.parse_expression(Precedence::Lowest)?;
+                self.expect_peek(TokenKind::RBracket)?;
+                Some(Expression::Index(lhs, Box::new(index)))
+            }
+            kind => {
+                let operator =
+                    infix_operator(kind).expect("only operators bind tighter than Lowest");
+                let precedence = precedence(kind);
+                self.advance();
+                let rhs = self.parse_expression(precedence)?;
+                Some(Expression::Infix(operator, lhs, Box::new(rhs)))
+            }
+        }
+    }
+
+    /// Comma separated expressions up to `end`, such as call arguments.
+    fn parse_list(&mut self, end: TokenKind) -> Option<Vec<Expression>> {
+        let mut elements = vec![];
+        if self.peek_is(end) {
+            self.advance();
+            return Some(elements);
+        }
+
+        self.advance();
+        elements.push(self.parse_expression(Precedence::Lowest)?);
+        while self.peek_is(TokenKind::Comma) {
+            self.advance();
+            self.advance();
+            elements.push(self.parse_expression(Precedence::Lowest)?);
+        }
+        self.expect_peek(end)?;
+
+        Some(elements)
+    }
+
+    fn parse_hash(&mut self) -> Option<Expression> {
+        let mut pairs = vec![];
+
+        while !self.peek_is(TokenKind::RBrace) {
+            self.advance();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            self.expect_peek(TokenKind::Colon)?;
+            self.advance();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+
+            if !self.peek_is(TokenKind::RBrace) {
+                self.expect_peek(TokenKind::Comma)?;
+            }
+        }
+        self.expect_peek(TokenKind::RBrace)?;
+
+        Some(Expression::Hash(pairs))
+    }
+
+    fn parse_if(&mut self) -> Option<Expression> {
+        self.expect_peek(TokenKind::LParen)?;
+        self.advance();
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(TokenKind::RParen)?;
+        self.expect_peek(TokenKind::LBrace)?;
+        let consequence = self.parse_block()?;
+
+        let alternative = if self.peek_is(TokenKind::Else) {
+            self.advance();
+            self.expect_peek(TokenKind::LBrace)?;
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+
+        Some(Expression::If {
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+        })
+    }
+
+    fn parse_function(&mut self) -> Option<Expression> {
+        self.expect_peek(TokenKind::LParen)?;
+
+        let mut parameters = vec![];
+        if self.peek_is(TokenKind::RParen) {
+            self.advance();
+        } else {
+            self.expect_peek(TokenKind::Ident)?;
+            parameters.push(self.current.literal.clone());
+            while self.peek_is(TokenKind::Comma) {
+                self.advance();
+                self.expect_peek(TokenKind::Ident)?;
+                parameters.push(self.current.literal.clone());
+            }
+            self.expect_peek(TokenKind::RParen)?;
+        }
+
+        self.expect_peek(TokenKind::LBrace)?;
+        let body = self.parse_block()?;
+
+        Some(Expression::Function { parameters, body })
+    }
+
+    fn parse_block(&mut self) -> Option<Block> {
+        let mut statements = vec![];
+        self.advance();
+
+        while self.current.kind != TokenKind::RBrace {
+            if self.current.kind == TokenKind::Eof {
+                let message = format!(
+                    "expected {}, got {} instead",
+                    TokenKind::RBrace,
+                    TokenKind::Eof
+                );
+                return self.error(self.current.position, message);
+            }
+            if let Some(statement) = self.parse_statement() {
+                statements.push(statement);
+            }
+            self.advance();
+        }
+
+        Some(Block { statements })
+    }
+}
+
+#[cfg(test)]
+fn parse_to_string(source: &str) -> String {
+    match parse(source) {
+        Ok(program) => program.to_string(),
+        Err(errors) => panic!("{:?} has errors: {:?}", source, errors),
+    }
+}
+
+#[cfg(test)]
+fn error_messages(source: &str) -> Vec<String> {
+    parse(source)
+        .expect_err(source)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn test_let_and_return_statements() {
+    assert_eq!(
+        parse("let x = 5; let y = true; let foobar = y; return 5; return x + y;"),
+        Ok(Program {
+            statements: vec![
+                Statement::Let {
+                    name: "x".to_string(),
+                    value: Expression::Integer(5),
+                },
+                Statement::Let {
+                    name: "y".to_string(),
+                    value: Expression::Boolean(true),
+                },
+                Statement::Let {
+                    name: "foobar".to_string(),
+                    value: Expression::Identifier("y".to_string()),
+                },
+                Statement::Return(Expression::Integer(5)),
+                Statement::Return(Expression::Infix(
+                    InfixOperator::Add,
+                    Box::new(Expression::Identifier("x".to_string())),
+                    Box::new(Expression::Identifier("y".to_string())),
+                )),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_literal_expressions() {
+    assert_eq!(parse_to_string("foobar;"), "foobar;");
+    assert_eq!(parse_to_string("5"), "5;");
+    assert_eq!(parse_to_string("true; false"), "true; false;");
+    assert_eq!(parse_to_string(r#""hello world";"#), r#""hello world";"#);
+    assert_eq!(parse_to_string("[]"), "[];");
+    assert_eq!(
+        parse_to_string("[1, 2 * 2, 3 + 3]"),
+        "[1, (2 * 2), (3 + 3)];"
+    );
+    assert_eq!(parse_to_string("{}"), "{};");
+    assert_eq!(
+        parse_to_string(r#"{"one": 1, "two": 2, "three": 3}"#),
+        r#"{"one": 1, "two": 2, "three": 3};"#
+    );
+    assert_eq!(
+        parse_to_string(r#"{"one": 0 + 1, true: 10 - 8, 3: 15 / 5}"#),
+        r#"{"one": (0 + 1), true: (10 - 8), 3: (15 / 5)};"#
+    );
+}
+
+#[test]
+fn test_operator_precedence() {
+    for (source, expected) in [
+        ("-a * b", "((-a) * b);"),
+        ("!-a", "(!(-a));"),
+        ("a + b + c", "((a + b) + c);"),
+        ("a + b - c", "((a + b) - c);"),
+        ("a * b * c", "((a * b) * c);"),
+        ("a * b / c", "((a * b) / c);"),
+        ("a + b / c", "(a + (b / c));"),
+        ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f);"),
+        ("3 + 4; -5 * 5", "(3 + 4); ((-5) * 5);"),
+        ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4));"),
+        ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4));"),
+        (
+            "3 + 4 * 5 == 3 * 1 + 4 * 5",
+            "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)));",
+        ),
+        ("3 > 5 == false", "((3 > 5) == false);"),
+        ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4);"),
+        ("(5 + 5) * 2", "((5 + 5) * 2);"),
+        ("2 / (5 + 5)", "(2 / (5 + 5));"),
+        ("-(5 + 5)", "(-(5 + 5));"),
+        ("!(true == true)", "(!(true == true));"),
+        ("a + add(b * c) + d", "((a + add((b * c))) + d);"),
+        (
+            "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+            "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)));",
+        ),
+        (
+            "add(a + b + c * d / f + g)",
+            "add((((a + b) + ((c * d) / f)) + g));",
+        ),
+        (
+            "a * [1, 2, 3, 4][b * c] * d",
+            "((a * ([1, 2, 3, 4][(b * c)])) * d);",
+        ),
+        (
+            "add(a * b[2], b[1], 2 * [1, 2][1])",
+            "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])));",
+        ),
+    ] {
+        assert_eq!(parse_to_string(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_if_and_function_expressions() {
+    assert_eq!(parse_to_string("if (x < y) { x }"), "if (x < y) { x; };");
+    assert_eq!(
+        parse_to_string("if (x < y) { x } else { y }"),
+        "if (x < y) { x; } else { y; };"
+    );
+    assert_eq!(
+        parse_to_string("fn(x, y) { x + y; }"),
+        "fn(x, y) { (x + y); };"
+    );
+    assert_eq!(parse_to_string("fn() {}"), "fn() { };");
+    assert_eq!(
+        parse_to_string("let f = fn(x) { return x; }; f(1)"),
+        "let f = fn(x) { return x; }; f(1);"
+    );
+    assert_eq!(parse_to_string("fn(x) { x }(5)"), "fn(x) { x; }(5);");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        error_messages("let = 5; let x 5; let 838383;"),
+        [
+            "1:5: expected next token to be IDENT, got = instead",
+            "1:5: no prefix parse function for = found",
+            "1:16: expected next token to be =, got INT instead",
+            "1:23: expected next token to be IDENT, got INT instead",
+        ]
+    );
+    assert_eq!(error_messages("1 + @"), ["1:5: illegal token `@`"]);
+    assert_eq!(
+        error_messages("99999999999999999999"),
+        ["1:1: could not parse 99999999999999999999 as integer"]
+    );
+    assert_eq!(
+        error_messages("if (x) { x"),
+        ["1:11: expected }, got EOF instead"]
+    );
+    // Parsing carries on after the `3`, so the `}` is an error too.
+    assert_eq!(
+        error_messages("{1: 2 3}"),
+        [
+            "1:7: expected next token to be ,, got INT instead",
+            "1:8: no prefix parse function for } found",
+        ]
+    );
+}